@@ -0,0 +1,319 @@
+use std::path::Path;
+
+use byteorder::{BigEndian, LittleEndian, ByteOrder as _};
+use sa2_set::{SetFileName, has_extension};
+use sa2_text::Language;
+use prs_util::decoder::Decoder;
+
+/// How much of a file is read to recognize it by its magic.
+pub const HEADER_LEN: usize = 0x20;
+/// Files up to this size are read whole, so compressed files can be looked
+/// inside and text tables checked. Bigger ones are only known by magic.
+pub const CONTENT_LIMIT: u64 = 4 << 20;
+
+const SET_HEADER_LEN: u64 = 32;
+const SET_OBJECT_LEN: u64 = 32;
+
+/// What a file in the game's directory holds.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord,Hash,Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// `set<level>_<s|u>.bin` object layouts.
+    SetFile,
+    /// A stage's hint messages, `eh<level><language>.prs` or `mh...`.
+    HintText,
+    /// Any other Strable string table.
+    TextTable,
+    /// An AFS archive, such as the voice and music banks.
+    Afs,
+    /// ADX audio.
+    Adx,
+    /// GameCube texture archive.
+    Gvm,
+    /// GameCube texture.
+    Gvr,
+    /// Dreamcast and PC texture archive.
+    Pvm,
+    /// Dreamcast and PC texture.
+    Pvr,
+    Unknown,
+}
+
+impl FileKind {
+    /// The crates in this workspace that can read or write the kind of file.
+    pub fn handled_by(&self) -> &'static [&'static str] {
+        match *self {
+            FileKind::SetFile => &["sa2_set", "set_editor", "stage_project"],
+            FileKind::HintText => &["sa2_text", "edit_hints", "stage_project"],
+            FileKind::TextTable => &["strable", "sa2_text"],
+            FileKind::Afs => &["afs_util", "stage_project"],
+            FileKind::Gvm | FileKind::Gvr | FileKind::Pvm | FileKind::Pvr => &["stage_project"],
+            FileKind::Adx | FileKind::Unknown => &[],
+        }
+    }
+}
+
+/// The byte order a set file was written in: big for PC and GameCube,
+/// little for Dreamcast.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrder {
+    Big,
+    Little,
+}
+
+/// What's known about a file after looking at its name and contents.
+#[derive(Clone,Debug,PartialEq,Serialize)]
+pub struct Identified {
+    pub kind: FileKind,
+    /// Whether the file is PRS compressed; `kind` is then what's inside.
+    pub compressed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_order: Option<ByteOrder>,
+    /// The level a set or hint file belongs to, from its name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+}
+
+impl Identified {
+    pub(crate) fn new(kind: FileKind) -> Identified {
+        Identified {
+            kind,
+            compressed: false,
+            byte_order: None,
+            level: None,
+            language: None,
+        }
+    }
+}
+
+/// Identifies the file at `path` from its name and `data`, which is either
+/// the whole file or at least its first `HEADER_LEN` bytes. `size` is the
+/// size of the whole file.
+pub fn identify(path: &Path, data: &[u8], size: u64) -> Identified {
+    let whole = data.len() as u64 == size;
+    if whole && has_extension(path, "prs") {
+        if let Ok(decoded) = Decoder::new(data).decode_to_vec() {
+            let mut identified = identify_contents(path, &decoded, decoded.len() as u64);
+            identified.compressed = true;
+            return identified;
+        }
+    }
+    identify_contents(path, data, size)
+}
+
+fn identify_contents(path: &Path, data: &[u8], size: u64) -> Identified {
+    if let Some(kind) = kind_from_magic(data) {
+        return Identified::new(kind);
+    }
+
+    if let Some(name) = SetFileName::from_path(path) {
+        if let Some(byte_order) = set_byte_order(data, size) {
+            let mut identified = Identified::new(FileKind::SetFile);
+            identified.byte_order = Some(byte_order);
+            identified.level = Some(name.level);
+            return identified;
+        }
+    }
+
+    if data.len() as u64 == size && is_strable(data) {
+        return match hint_name(path) {
+            Some((level, language)) => {
+                let mut identified = Identified::new(FileKind::HintText);
+                identified.level = Some(level);
+                identified.language = Some(language);
+                identified
+            }
+            None => Identified::new(FileKind::TextTable),
+        };
+    }
+
+    Identified::new(FileKind::Unknown)
+}
+
+fn kind_from_magic(data: &[u8]) -> Option<FileKind> {
+    // Textures may have a global index chunk in front of the texture itself.
+    let texture = if data.starts_with(b"GBIX") || data.starts_with(b"GCIX") {
+        data.get(0x10..).unwrap_or(&[])
+    }
+    else {
+        data
+    };
+
+    if data.starts_with(b"AFS\0") {
+        Some(FileKind::Afs)
+    }
+    else if data.starts_with(b"GVMH") {
+        Some(FileKind::Gvm)
+    }
+    else if data.starts_with(b"PVMH") {
+        Some(FileKind::Pvm)
+    }
+    else if texture.starts_with(b"GVRT") {
+        Some(FileKind::Gvr)
+    }
+    else if texture.starts_with(b"PVRT") {
+        Some(FileKind::Pvr)
+    }
+    else if is_adx(data) {
+        Some(FileKind::Adx)
+    }
+    else {
+        None
+    }
+}
+
+/// ADX files start with 0x8000 and the offset to their audio, which is
+/// preceded by a "(c)CRI" copyright notice.
+fn is_adx(data: &[u8]) -> bool {
+    if data.len() < 4 || data[0] != 0x80 || data[1] != 0x00 {
+        return false;
+    }
+    let copyright = BigEndian::read_u16(&data[2..4]) as usize;
+    match data.get(copyright.wrapping_sub(2)..copyright + 4) {
+        Some(notice) => notice == b"(c)CRI",
+        // Only the header was read, so trust the signature.
+        None => data.len() == HEADER_LEN,
+    }
+}
+
+/// A set file is a 32 byte header starting with its object count, then 32
+/// bytes per object. Whichever byte order makes the count fit the size is
+/// the one it was written in.
+fn set_byte_order(data: &[u8], size: u64) -> Option<ByteOrder> {
+    if data.len() < 4 || size < SET_HEADER_LEN {
+        return None;
+    }
+    let count = (size - SET_HEADER_LEN) / SET_OBJECT_LEN;
+    let leftover = (size - SET_HEADER_LEN) % SET_OBJECT_LEN;
+    if leftover != 0 {
+        return None;
+    }
+    if BigEndian::read_u32(data) as u64 == count {
+        Some(ByteOrder::Big)
+    }
+    else if LittleEndian::read_u32(data) as u64 == count {
+        Some(ByteOrder::Little)
+    }
+    else {
+        None
+    }
+}
+
+/// A Strable is a table of big endian offsets ending in 0xffffffff, with the
+/// first string right after the table and the rest following in order.
+fn is_strable(data: &[u8]) -> bool {
+    let mut offsets = Vec::new();
+    let mut terminated = false;
+    for entry in data.chunks(4) {
+        if entry.len() < 4 {
+            return false;
+        }
+        let offset = BigEndian::read_u32(entry);
+        if offset == 0xffffffff {
+            terminated = true;
+            break;
+        }
+        offsets.push(offset as usize);
+    }
+
+    let table_len = (offsets.len() + 1) * 4;
+    terminated
+        && !offsets.is_empty()
+        && offsets[0] == table_len
+        && offsets.windows(2).all(|pair| pair[0] < pair[1])
+        && offsets.last().map(|last| *last < data.len()).unwrap_or(false)
+}
+
+/// Hint files are named `eh` or `mh`, a four digit level and a language
+/// letter, e.g. `eh0013e.prs`.
+fn hint_name(path: &Path) -> Option<(u16, Language)> {
+    let stem = path.file_stem()?.to_str()?.to_lowercase();
+    if stem.len() != 7 || !(stem.starts_with("eh") || stem.starts_with("mh")) {
+        return None;
+    }
+    let level = stem[2..6].parse().ok()?;
+    let language = match &stem[6..] {
+        "j" => Language::Japanese,
+        "e" => Language::English,
+        "f" => Language::French,
+        "s" => Language::Spanish,
+        "g" => Language::German,
+        _ => return None,
+    };
+    Some((level, language))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use prs_util::encoder::Encoder;
+    use sa2_text::Language;
+
+    use super::{identify, ByteOrder, FileKind};
+
+    fn strable(strings: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut offset = (strings.len() + 1) * 4;
+        for string in strings {
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            offset += string.len() + 1;
+        }
+        data.extend_from_slice(&[0xff; 4]);
+        for string in strings {
+            data.extend_from_slice(string);
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn identifies_by_magic() {
+        let mut gvr = b"GBIX\x08\0\0\0\0\0\0\0\0\0\0\0GVRT".to_vec();
+        gvr.resize(0x40, 0);
+        assert_eq!(identify(Path::new("a.gvr"), &gvr, 0x40).kind, FileKind::Gvr);
+        assert_eq!(identify(Path::new("voice.afs"), b"AFS\0\x02\0\0\0", 8).kind, FileKind::Afs);
+        assert_eq!(identify(Path::new("stage.gvm"), b"GVMH", 4).kind, FileKind::Gvm);
+
+        let mut adx = vec![0x80, 0x00, 0x00, 0x20];
+        adx.resize(0x1e, 0);
+        adx.extend_from_slice(b"(c)CRI");
+        assert_eq!(identify(Path::new("0.adx"), &adx, adx.len() as u64).kind, FileKind::Adx);
+        assert_eq!(identify(Path::new("0.adx"), &adx[..4], adx.len() as u64).kind, FileKind::Unknown);
+    }
+
+    #[test]
+    fn identifies_set_files_and_byte_order() {
+        let mut big = vec![0, 0, 0, 2];
+        big.resize(32 + 2 * 32, 0);
+        let identified = identify(Path::new("set0013_s.bin"), &big, big.len() as u64);
+        assert_eq!(identified.kind, FileKind::SetFile);
+        assert_eq!(identified.byte_order, Some(ByteOrder::Big));
+        assert_eq!(identified.level, Some(13));
+
+        let mut little = vec![2, 0, 0, 0];
+        little.resize(32 + 2 * 32, 0);
+        let identified = identify(Path::new("set0013_hd_u.bin"), &little[..super::HEADER_LEN], little.len() as u64);
+        assert_eq!(identified.byte_order, Some(ByteOrder::Little));
+
+        assert_eq!(identify(Path::new("set0013_s.bin"), &big, 100).kind, FileKind::Unknown);
+    }
+
+    #[test]
+    fn looks_inside_compressed_text() {
+        let table = strable(&[b"\x0cs10 \x07Hello", b"\x07Bye"]);
+        let compressed = Encoder::new(&table).encode();
+        let identified = identify(Path::new("eh0013e.prs"), &compressed, compressed.len() as u64);
+        assert_eq!(identified.kind, FileKind::HintText);
+        assert!(identified.compressed);
+        assert_eq!(identified.level, Some(13));
+        assert_eq!(identified.language, Some(Language::English));
+
+        let identified = identify(Path::new("msgalkinder.bin"), &table, table.len() as u64);
+        assert_eq!(identified.kind, FileKind::TextTable);
+        assert!(!identified.compressed);
+    }
+}
@@ -0,0 +1,14 @@
+extern crate sa2_set;
+extern crate sa2_text;
+extern crate prs_util;
+extern crate byteorder;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate walkdir;
+
+pub mod identify;
+pub mod scan;
+
+pub use identify::{identify, ByteOrder, FileKind, Identified};
+pub use scan::{scan, Inventory, ScannedFile};
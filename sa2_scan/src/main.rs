@@ -0,0 +1,25 @@
+extern crate sa2_scan;
+extern crate serde_json;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: sa2_scan GAME_DIR";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 1 {
+        println!("{}", USAGE);
+        process::exit(1);
+    }
+
+    let inventory = sa2_scan::scan(Path::new(&args[0])).unwrap_or_else(|e| barf(&e.to_string()));
+    let json = serde_json::to_string_pretty(&inventory).unwrap_or_else(|e| barf(&e.to_string()));
+    println!("{}", json);
+}
+
+fn barf(err: &str) -> ! {
+    println!("Error: {}", err);
+    process::exit(1);
+}
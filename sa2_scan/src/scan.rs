@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+use sa2_set::PlatformKind;
+
+use identify::{self, ByteOrder, FileKind, Identified};
+
+/// Every file found under a game directory and what it is.
+#[derive(Clone,Debug,PartialEq,Serialize)]
+pub struct Inventory {
+    pub root: PathBuf,
+    /// The platform the directory was extracted from, or `None` if nothing
+    /// gave it away.
+    pub platform: Option<PlatformKind>,
+    /// How many files there are of each kind.
+    pub totals: BTreeMap<FileKind, usize>,
+    pub files: Vec<ScannedFile>,
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize)]
+pub struct ScannedFile {
+    /// Relative to the game directory.
+    pub path: PathBuf,
+    pub size: u64,
+    #[serde(flatten)]
+    pub identified: Identified,
+    pub handled_by: &'static [&'static str],
+    /// Why the file couldn't be read, if it couldn't. Its kind is then
+    /// unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ScannedFile {
+    fn unreadable(path: PathBuf, err: &dyn Display) -> ScannedFile {
+        ScannedFile {
+            path,
+            size: 0,
+            identified: Identified::new(FileKind::Unknown),
+            handled_by: FileKind::Unknown.handled_by(),
+            error: Some(err.to_string()),
+        }
+    }
+}
+
+/// Walks `root` and identifies every file in it, in path order. Files and
+/// directories that can't be read are listed with their error rather than
+/// stopping the scan; only an unreadable `root` fails it.
+pub fn scan(root: &Path) -> io::Result<Inventory> {
+    fs::metadata(root)?;
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(root).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_file() {
+                    files.push(scan_file(entry.path(), relative(entry.path())));
+                }
+            }
+            Err(err) => files.push(ScannedFile::unreadable(relative(err.path().unwrap_or(root)), &err)),
+        }
+    }
+
+    let mut totals = BTreeMap::new();
+    for file in files.iter() {
+        *totals.entry(file.identified.kind).or_insert(0) += 1;
+    }
+
+    Ok(Inventory {
+        root: root.to_path_buf(),
+        platform: detect_platform(&files),
+        totals,
+        files,
+    })
+}
+
+/// Identifies the file at `path`, listed as `relative`.
+fn scan_file(path: &Path, relative: PathBuf) -> ScannedFile {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) => return ScannedFile::unreadable(relative, &err),
+    };
+    let data = match read_for_identify(path, size) {
+        Ok(data) => data,
+        Err(err) => return ScannedFile::unreadable(relative, &err),
+    };
+
+    let identified = identify::identify(path, &data, size);
+    ScannedFile {
+        path: relative,
+        size,
+        handled_by: identified.kind.handled_by(),
+        identified,
+        error: None,
+    }
+}
+
+/// Reads all of a small file, or just the header of a big one.
+fn read_for_identify(path: &Path, size: u64) -> io::Result<Vec<u8>> {
+    let limit = if size <= identify::CONTENT_LIMIT { size } else { identify::HEADER_LEN as u64 };
+    let mut data = Vec::with_capacity(limit as usize);
+    File::open(path)?.take(limit).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Looks for files only one platform's release has, falling back to the
+/// byte order of the set files: only Dreamcast's are little endian.
+fn detect_platform(files: &[ScannedFile]) -> Option<PlatformKind> {
+    let marker = files.iter()
+        .flat_map(|file| file.path.components())
+        .filter_map(|component| component.as_os_str().to_str())
+        .filter_map(marker_platform)
+        .next();
+
+    marker.or_else(|| {
+        let little = files.iter().any(|file| file.identified.byte_order == Some(ByteOrder::Little));
+        if little { Some(PlatformKind::Dreamcast) } else { None }
+    })
+}
+
+fn marker_platform(name: &str) -> Option<PlatformKind> {
+    match name.to_lowercase().as_str() {
+        "sonic2app.exe" | "gd_pc" => Some(PlatformKind::Pc),
+        "&&systemdata" | "main.dol" | "opening.bnr" => Some(PlatformKind::GameCube),
+        "1st_read.bin" | "ip.bin" => Some(PlatformKind::Dreamcast),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use identify::FileKind;
+    use sa2_set::PlatformKind;
+    use super::{scan, scan_file};
+
+    #[test]
+    fn scans_a_game_directory() {
+        let root = env::temp_dir().join(format!("sa2_scan_{}", ::std::process::id()));
+        fs::create_dir_all(root.join("resource/gd_PC/PRS")).unwrap();
+
+        let mut set_data = vec![0, 0, 0, 1];
+        set_data.resize(64, 0);
+        fs::write(root.join("resource/gd_PC/set0013_s.bin"), &set_data).unwrap();
+        fs::write(root.join("resource/gd_PC/PRS/stage.gvm"), b"GVMH").unwrap();
+        fs::write(root.join("resource/gd_PC/readme.txt"), b"hello").unwrap();
+        let inventory = scan(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(inventory.platform, Some(PlatformKind::Pc));
+        assert_eq!(inventory.files.len(), 3);
+        assert_eq!(inventory.files[0].path, Path::new("resource/gd_PC/PRS/stage.gvm"));
+        assert_eq!(inventory.files[2].identified.kind, FileKind::SetFile);
+        assert_eq!(inventory.files[2].handled_by[0], "sa2_set");
+        assert_eq!(inventory.totals[&FileKind::Unknown], 1);
+        assert_eq!(inventory.files[1].error, None);
+    }
+
+    #[test]
+    fn lists_unreadable_files() {
+        let path = env::temp_dir().join(format!("sa2_scan_missing_{}", ::std::process::id()));
+        let scanned = scan_file(&path, PathBuf::from("missing.bin"));
+        assert_eq!(scanned.path, Path::new("missing.bin"));
+        assert_eq!(scanned.identified.kind, FileKind::Unknown);
+        assert!(scanned.error.is_some());
+
+        assert!(scan(&path).is_err());
+    }
+}
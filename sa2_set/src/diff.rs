@@ -0,0 +1,103 @@
+use {SetFile, Position};
+
+/// How one object differs between two versions of a set file. Indices refer
+/// to the old and new `SetFile` respectively.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Change {
+    Unchanged(usize, usize),
+    /// Same object ID at a different position. Rotation and attributes may
+    /// have changed too.
+    Moved(usize, usize),
+    /// Same object ID and position with a different rotation or attributes.
+    Modified(usize, usize),
+    Added(usize),
+    Removed(usize),
+}
+
+/// Pairs up the objects of `old` and `new`.
+///
+/// Identical objects are paired first. Leftover objects with the same ID are
+/// then paired closest-first and reported as moved or modified; whatever is
+/// still unpaired was added or removed.
+pub fn diff(old: &SetFile, new: &SetFile) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut old_used = vec![false; old.0.len()];
+    let mut new_used = vec![false; new.0.len()];
+
+    for (new_idx, new_obj) in new.0.iter().enumerate() {
+        let found = old.0.iter()
+            .enumerate()
+            .position(|(old_idx, old_obj)| !old_used[old_idx] && old_obj == new_obj);
+        if let Some(old_idx) = found {
+            old_used[old_idx] = true;
+            new_used[new_idx] = true;
+            changes.push(Change::Unchanged(old_idx, new_idx));
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (old_idx, old_obj) in old.0.iter().enumerate().filter(|&(idx, _)| !old_used[idx]) {
+        for (new_idx, new_obj) in new.0.iter().enumerate().filter(|&(idx, _)| !new_used[idx]) {
+            if old_obj.object.0 == new_obj.object.0 {
+                candidates.push((distance_squared(&old_obj.position, &new_obj.position), old_idx, new_idx));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+
+    for (dist_sq, old_idx, new_idx) in candidates {
+        if old_used[old_idx] || new_used[new_idx] {
+            continue;
+        }
+        old_used[old_idx] = true;
+        new_used[new_idx] = true;
+
+        if dist_sq == 0.0 {
+            changes.push(Change::Modified(old_idx, new_idx));
+        }
+        else {
+            changes.push(Change::Moved(old_idx, new_idx));
+        }
+    }
+
+    changes.extend(old_used.iter().enumerate().filter(|&(_, used)| !used).map(|(idx, _)| Change::Removed(idx)));
+    changes.extend(new_used.iter().enumerate().filter(|&(_, used)| !used).map(|(idx, _)| Change::Added(idx)));
+    changes
+}
+
+fn distance_squared(a: &Position, b: &Position) -> f32 {
+    (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y) + (a.z - b.z) * (a.z - b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use {SetFile, SetObject, Object, Position};
+
+    use super::{diff, Change};
+
+    fn obj(id: u16, x: f32) -> SetObject {
+        SetObject {
+            object: Object(id),
+            position: Position { x, y: 0.0, z: 0.0 },
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn classify_changes() {
+        let old = SetFile(vec![obj(0, 0.0), obj(1, 10.0), obj(2, 20.0), obj(3, 30.0)]);
+        let mut modified = obj(2, 20.0);
+        modified.attr1 = 5.0;
+        let new = SetFile(vec![obj(4, 40.0), obj(0, 0.0), obj(1, 15.0), modified]);
+
+        let mut changes = diff(&old, &new);
+        changes.sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(changes, vec![
+            Change::Added(0),
+            Change::Modified(2, 3),
+            Change::Moved(1, 2),
+            Change::Removed(3),
+            Change::Unchanged(0, 1),
+        ]);
+    }
+}
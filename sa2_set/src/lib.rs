@@ -1,14 +1,23 @@
 extern crate byteorder;
 #[macro_use] extern crate serde_derive;
 
+pub mod stage;
+pub mod transform;
+pub mod spatial;
+pub mod diff;
+
+pub use stage::{Stage, SetFileName, SetKind, SetVariant, OBJECT_LIMIT, has_extension};
+pub use transform::{Query, Filter, Condition, Transform};
+pub use spatial::SpatialIndex;
+
 use std::io::{self, Read, Write};
 
 use byteorder::{ReadBytesExt, WriteBytesExt, ByteOrder, LittleEndian, BigEndian};
 
-#[derive(Clone,Copy,Debug,Default,Serialize,Deserialize)]
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
 pub struct Object(pub u16);
 
-#[derive(Clone,Copy,Debug,Default,Serialize,Deserialize)]
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
 pub struct Rotation {
     pub x: u16,
     pub y: u16,
@@ -42,7 +51,7 @@ impl Rotation {
     }
 }
 
-#[derive(Clone,Copy,Debug,Default,Serialize,Deserialize)]
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
@@ -76,7 +85,7 @@ impl Position {
     }
 }
 
-#[derive(Clone,Copy,Debug,Default,Serialize,Deserialize)]
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
 pub struct SetObject {
     pub object: Object,
     pub rotation: Rotation,
@@ -177,6 +186,16 @@ pub trait Platform {
     type Endianess: ByteOrder;
 }
 
+/// One of the `Platform`s, for when it's picked at runtime: read from a
+/// project, a settings file or a scan of the game's files.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformKind {
+    Pc,
+    GameCube,
+    Dreamcast,
+}
+
 pub struct Dreamcast;
 
 impl Platform for Dreamcast {
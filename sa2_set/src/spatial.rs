@@ -0,0 +1,226 @@
+use std::cmp::Ordering;
+
+use {SetFile, Position};
+
+/// A k-d tree over the positions of a set file's objects.
+///
+/// Queries return object indices into the `SetFile` the index was built
+/// from, so the index has to be rebuilt whenever objects move, are added or
+/// are removed.
+#[derive(Clone,Debug)]
+pub struct SpatialIndex {
+    // Implicit tree: each sub-slice's median element is the node, the
+    // elements before it the left subtree and the ones after it the right.
+    points: Vec<(usize, [f32; 3])>,
+}
+
+impl SpatialIndex {
+    pub fn new(set_file: &SetFile) -> SpatialIndex {
+        let mut points: Vec<_> = set_file.0.iter()
+            .enumerate()
+            .map(|(idx, obj)| (idx, to_array(&obj.position)))
+            .collect();
+        build(&mut points, 0);
+
+        SpatialIndex { points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The `count` objects closest to `point` as `(index, distance)` pairs,
+    /// closest first.
+    pub fn nearest(&self, point: &Position, count: usize) -> Vec<(usize, f32)> {
+        let mut best = Vec::with_capacity(count + 1);
+        if count > 0 {
+            nearest(&self.points, 0, &to_array(point), count, &mut best);
+        }
+        best.into_iter().map(|(idx, dist_sq)| (idx, dist_sq.sqrt())).collect()
+    }
+
+    /// Every object within `radius` of `point` as `(index, distance)` pairs,
+    /// closest first.
+    pub fn within_radius(&self, point: &Position, radius: f32) -> Vec<(usize, f32)> {
+        let mut found = Vec::new();
+        within_radius(&self.points, 0, &to_array(point), radius * radius, &mut found);
+        found.sort_by(|a, b| compare(a.1, b.1).then(a.0.cmp(&b.0)));
+        found.into_iter().map(|(idx, dist_sq)| (idx, dist_sq.sqrt())).collect()
+    }
+
+    /// Every object inside the axis-aligned box spanned by two corners, in
+    /// object order.
+    pub fn within_box(&self, corner1: &Position, corner2: &Position) -> Vec<usize> {
+        let (a, b) = (to_array(corner1), to_array(corner2));
+        let min = [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])];
+        let max = [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])];
+
+        let mut found = Vec::new();
+        within_box(&self.points, 0, &min, &max, &mut found);
+        found.sort();
+        found
+    }
+}
+
+fn to_array(position: &Position) -> [f32; 3] {
+    [position.x, position.y, position.z]
+}
+
+fn compare(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|axis| (a[axis] - b[axis]) * (a[axis] - b[axis])).sum()
+}
+
+fn build(points: &mut [(usize, [f32; 3])], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| compare(a.1[axis], b.1[axis]));
+
+    let (left, right) = points.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn nearest(points: &[(usize, [f32; 3])], depth: usize, target: &[f32; 3], count: usize, best: &mut Vec<(usize, f32)>) {
+    if points.is_empty() {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    let (idx, point) = points[mid];
+
+    let dist_sq = distance_squared(&point, target);
+    let insert_at = best.iter()
+        .position(|&(best_idx, best_dist)| compare(dist_sq, best_dist).then(idx.cmp(&best_idx)) == Ordering::Less)
+        .unwrap_or(best.len());
+    if insert_at < count {
+        best.insert(insert_at, (idx, dist_sq));
+        best.truncate(count);
+    }
+
+    let plane = target[axis] - point[axis];
+    let (near, far) = if plane < 0.0 {
+        (&points[..mid], &points[mid + 1..])
+    }
+    else {
+        (&points[mid + 1..], &points[..mid])
+    };
+
+    nearest(near, depth + 1, target, count, best);
+    if best.len() < count || plane * plane <= best[best.len() - 1].1 {
+        nearest(far, depth + 1, target, count, best);
+    }
+}
+
+fn within_radius(points: &[(usize, [f32; 3])], depth: usize, target: &[f32; 3], radius_sq: f32, found: &mut Vec<(usize, f32)>) {
+    if points.is_empty() {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    let (idx, point) = points[mid];
+
+    let dist_sq = distance_squared(&point, target);
+    if dist_sq <= radius_sq {
+        found.push((idx, dist_sq));
+    }
+
+    let plane = target[axis] - point[axis];
+    if plane <= 0.0 || plane * plane <= radius_sq {
+        within_radius(&points[..mid], depth + 1, target, radius_sq, found);
+    }
+    if plane >= 0.0 || plane * plane <= radius_sq {
+        within_radius(&points[mid + 1..], depth + 1, target, radius_sq, found);
+    }
+}
+
+fn within_box(points: &[(usize, [f32; 3])], depth: usize, min: &[f32; 3], max: &[f32; 3], found: &mut Vec<usize>) {
+    if points.is_empty() {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    let (idx, point) = points[mid];
+
+    if (0..3).all(|a| point[a] >= min[a] && point[a] <= max[a]) {
+        found.push(idx);
+    }
+
+    if min[axis] <= point[axis] {
+        within_box(&points[..mid], depth + 1, min, max, found);
+    }
+    if max[axis] >= point[axis] {
+        within_box(&points[mid + 1..], depth + 1, min, max, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {SetFile, SetObject, Position};
+
+    use super::SpatialIndex;
+
+    fn grid() -> SetFile {
+        let mut objs = Vec::new();
+        for x in 0..10 {
+            for z in 0..10 {
+                objs.push(SetObject {
+                    position: Position { x: x as f32 * 10.0, y: (x * z) as f32, z: z as f32 * 10.0 },
+                    ..SetObject::default()
+                });
+            }
+        }
+        SetFile(objs)
+    }
+
+    fn brute_force(set_file: &SetFile, point: &Position) -> Vec<(usize, f32)> {
+        let mut all: Vec<_> = set_file.0.iter()
+            .enumerate()
+            .map(|(idx, obj)| {
+                let (dx, dy, dz) = (obj.position.x - point.x, obj.position.y - point.y, obj.position.z - point.z);
+                (idx, (dx * dx + dy * dy + dz * dz).sqrt())
+            })
+            .collect();
+        all.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)));
+        all
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let set_file = grid();
+        let index = SpatialIndex::new(&set_file);
+        let point = Position { x: 33.0, y: 7.0, z: 61.0 };
+
+        let expected: Vec<_> = brute_force(&set_file, &point).into_iter().take(7).map(|(idx, _)| idx).collect();
+        let found: Vec<_> = index.nearest(&point, 7).into_iter().map(|(idx, _)| idx).collect();
+        assert_eq!(found, expected);
+        assert!(SpatialIndex::new(&SetFile(Vec::new())).nearest(&point, 3).is_empty());
+    }
+
+    #[test]
+    fn radius_and_box_queries() {
+        let set_file = grid();
+        let index = SpatialIndex::new(&set_file);
+        let point = Position { x: 50.0, y: 0.0, z: 50.0 };
+
+        let expected: Vec<_> = brute_force(&set_file, &point).into_iter().filter(|&(_, d)| d <= 30.0).collect();
+        assert_eq!(index.within_radius(&point, 30.0), expected);
+
+        let found = index.within_box(&Position { x: 15.0, y: -1.0, z: 0.0 }, &Position { x: 35.0, y: 30.0, z: 10.0 });
+        assert_eq!(found, vec![20, 21, 30, 31]);
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+use std::path::Path;
+
+/// Number of set object slots the game allocates for a single set file.
+/// Objects past this count are silently dropped when the stage loads.
+pub const OBJECT_LIMIT: usize = 1024;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub struct Stage {
+    pub id: u16,
+    pub name: &'static str,
+}
+
+pub const STAGES: &[Stage] = &[
+    Stage { id: 0, name: "Basic Test" },
+    Stage { id: 1, name: "Knuckles Test" },
+    Stage { id: 2, name: "Sonic Test" },
+    Stage { id: 3, name: "Green Forest" },
+    Stage { id: 4, name: "White Jungle" },
+    Stage { id: 5, name: "Pumpkin Hill" },
+    Stage { id: 6, name: "Sky Rail" },
+    Stage { id: 7, name: "Aquatic Mine" },
+    Stage { id: 8, name: "Security Hall" },
+    Stage { id: 9, name: "Prison Lane" },
+    Stage { id: 10, name: "Metal Harbor" },
+    Stage { id: 11, name: "Iron Gate" },
+    Stage { id: 12, name: "Weapons Bed" },
+    Stage { id: 13, name: "City Escape" },
+    Stage { id: 14, name: "Radical Highway" },
+    Stage { id: 15, name: "Weapons Bed 2P" },
+    Stage { id: 16, name: "Wild Canyon" },
+    Stage { id: 17, name: "Mission Street" },
+    Stage { id: 18, name: "Dry Lagoon" },
+    Stage { id: 19, name: "Sonic vs. Shadow 1" },
+    Stage { id: 20, name: "Tails vs. Eggman 1" },
+    Stage { id: 21, name: "Sand Ocean" },
+    Stage { id: 22, name: "Crazy Gadget" },
+    Stage { id: 23, name: "Hidden Base" },
+    Stage { id: 24, name: "Eternal Engine" },
+    Stage { id: 25, name: "Death Chamber" },
+    Stage { id: 26, name: "Egg Quarters" },
+    Stage { id: 27, name: "Lost Colony" },
+    Stage { id: 28, name: "Pyramid Cave" },
+    Stage { id: 29, name: "Tails vs. Eggman 2" },
+    Stage { id: 30, name: "Final Rush" },
+    Stage { id: 31, name: "Green Hill" },
+    Stage { id: 32, name: "Meteor Herd" },
+    Stage { id: 33, name: "Knuckles vs. Rouge" },
+    Stage { id: 34, name: "Cannon's Core (Sonic)" },
+    Stage { id: 35, name: "Cannon's Core (Eggman)" },
+    Stage { id: 36, name: "Cannon's Core (Tails)" },
+    Stage { id: 37, name: "Cannon's Core (Rouge)" },
+    Stage { id: 38, name: "Cannon's Core (Knuckles)" },
+    Stage { id: 39, name: "Mission Street 2P" },
+    Stage { id: 40, name: "Final Chase" },
+    Stage { id: 41, name: "Wild Canyon 2P" },
+    Stage { id: 42, name: "Sonic vs. Shadow 2" },
+    Stage { id: 43, name: "Cosmic Wall" },
+    Stage { id: 44, name: "Mad Space" },
+    Stage { id: 45, name: "Sand Ocean 2P" },
+    Stage { id: 46, name: "Dry Lagoon 2P" },
+    Stage { id: 47, name: "Pyramid Race" },
+    Stage { id: 48, name: "Hidden Base 2P" },
+    Stage { id: 49, name: "Pool Quest" },
+    Stage { id: 50, name: "Planet Quest" },
+    Stage { id: 51, name: "Deck Race" },
+    Stage { id: 52, name: "Downtown Race" },
+    Stage { id: 53, name: "Cosmic Wall 2P" },
+    Stage { id: 54, name: "Grind Race" },
+    Stage { id: 55, name: "Lost Colony 2P" },
+    Stage { id: 56, name: "Eternal Engine 2P" },
+    Stage { id: 57, name: "Metal Harbor 2P" },
+    Stage { id: 58, name: "Iron Gate 2P" },
+    Stage { id: 59, name: "Death Chamber 2P" },
+    Stage { id: 60, name: "Big Foot" },
+    Stage { id: 61, name: "Hot Shot" },
+    Stage { id: 62, name: "Flying Dog" },
+    Stage { id: 63, name: "King Boom Boo" },
+    Stage { id: 64, name: "Egg Golem (Sonic)" },
+    Stage { id: 65, name: "Biolizard" },
+    Stage { id: 66, name: "Finalhazard" },
+    Stage { id: 67, name: "Egg Golem (Eggman)" },
+    Stage { id: 70, name: "Route 101" },
+    Stage { id: 71, name: "Route 280" },
+];
+
+impl Stage {
+    pub fn from_id(id: u16) -> Option<Stage> {
+        STAGES.iter().find(|stage| stage.id == id).cloned()
+    }
+}
+
+/// Which of a stage's two set files an object list belongs to.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum SetKind {
+    /// `_s` set file, loaded for single-player.
+    S,
+    /// `_u` set file, loaded for 2P and hard mode.
+    U,
+}
+
+impl SetKind {
+    fn suffix(&self) -> &'static str {
+        match *self {
+            SetKind::S => "s",
+            SetKind::U => "u",
+        }
+    }
+}
+
+/// Alternate layouts of a stage that get their own pair of set files.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum SetVariant {
+    Normal,
+    /// `_2p` set files.
+    TwoPlayer,
+    /// `_hd` set files.
+    Hard,
+}
+
+impl SetVariant {
+    fn infix(&self) -> &'static str {
+        match *self {
+            SetVariant::Normal => "",
+            SetVariant::TwoPlayer => "_2p",
+            SetVariant::Hard => "_hd",
+        }
+    }
+}
+
+/// A set file name following the game's `set<level>[_2p|_hd]_<s|u>.bin` convention.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub struct SetFileName {
+    pub level: u16,
+    pub variant: SetVariant,
+    pub kind: SetKind,
+}
+
+impl SetFileName {
+    pub fn new(level: u16, variant: SetVariant, kind: SetKind) -> SetFileName {
+        SetFileName { level, variant, kind }
+    }
+
+    /// Parses the file stem of `path`, ignoring its extension so decoded
+    /// files such as `set0013_s.json` are recognized too.
+    pub fn from_path(path: &Path) -> Option<SetFileName> {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(SetFileName::from_stem)
+    }
+
+    fn from_stem(stem: &str) -> Option<SetFileName> {
+        let stem = stem.to_lowercase();
+        if !stem.starts_with("set") {
+            return None;
+        }
+
+        let rest = &stem[3..];
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let level = rest[..digits].parse().ok()?;
+        let rest = &rest[digits..];
+
+        let (variant, rest) = if let Some(rest) = rest.strip_prefix("_2p") {
+            (SetVariant::TwoPlayer, rest)
+        }
+        else if let Some(rest) = rest.strip_prefix("_hd") {
+            (SetVariant::Hard, rest)
+        }
+        else {
+            (SetVariant::Normal, rest)
+        };
+
+        let kind = match rest {
+            "_s" => SetKind::S,
+            "_u" => SetKind::U,
+            _ => return None,
+        };
+
+        Some(SetFileName::new(level, variant, kind))
+    }
+
+    pub fn stage(&self) -> Option<Stage> {
+        Stage::from_id(self.level)
+    }
+
+    /// How many of the file's objects the game loads. Only the limit shared
+    /// by every set file is known; smaller ones for particular stages or
+    /// kinds can go here once they're confirmed.
+    pub fn object_limit(&self) -> usize {
+        OBJECT_LIMIT
+    }
+}
+
+/// Whether `path` has the extension `extension`, ignoring case, since the
+/// game's files are named in upper case on some discs.
+pub fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case(extension)).unwrap_or(false)
+}
+
+impl fmt::Display for SetFileName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "set{:04}{}_{}.bin", self.level, self.variant.infix(), self.kind.suffix())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{SetFileName, SetKind, SetVariant};
+
+    #[test]
+    fn parse_set_file_names() {
+        assert_eq!(SetFileName::from_path(Path::new("set0013_s.bin")),
+                   Some(SetFileName::new(13, SetVariant::Normal, SetKind::S)));
+        assert_eq!(SetFileName::from_path(Path::new("dir/SET0016_2P_U.json")),
+                   Some(SetFileName::new(16, SetVariant::TwoPlayer, SetKind::U)));
+        assert_eq!(SetFileName::from_path(Path::new("set0030_hd_s.bin")),
+                   Some(SetFileName::new(30, SetVariant::Hard, SetKind::S)));
+        assert_eq!(SetFileName::from_path(Path::new("set0013.bin")), None);
+        assert_eq!(SetFileName::from_path(Path::new("obj_table.json")), None);
+    }
+
+    #[test]
+    fn set_file_name_round_trip() {
+        let name = SetFileName::new(13, SetVariant::Hard, SetKind::U);
+        assert_eq!(name.to_string(), "set0013_hd_u.bin");
+        assert_eq!(SetFileName::from_path(Path::new(&name.to_string())), Some(name));
+    }
+}
@@ -0,0 +1,429 @@
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use {SetFile, SetObject, Position};
+
+/// Numeric fields of a set object that conditions can test.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Field {
+    Object,
+    XRotation,
+    YRotation,
+    ZRotation,
+    XPosition,
+    YPosition,
+    ZPosition,
+    Attribute1,
+    Attribute2,
+    Attribute3,
+}
+
+impl Field {
+    pub fn get(&self, obj: &SetObject) -> f32 {
+        match *self {
+            Field::Object => obj.object.0 as f32,
+            Field::XRotation => obj.rotation.x as f32,
+            Field::YRotation => obj.rotation.y as f32,
+            Field::ZRotation => obj.rotation.z as f32,
+            Field::XPosition => obj.position.x,
+            Field::YPosition => obj.position.y,
+            Field::ZPosition => obj.position.z,
+            Field::Attribute1 => obj.attr1,
+            Field::Attribute2 => obj.attr2,
+            Field::Attribute3 => obj.attr3,
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(Field::Object),
+            "rx" => Ok(Field::XRotation),
+            "ry" => Ok(Field::YRotation),
+            "rz" => Ok(Field::ZRotation),
+            "x" => Ok(Field::XPosition),
+            "y" => Ok(Field::YPosition),
+            "z" => Ok(Field::ZPosition),
+            "attr1" => Ok(Field::Attribute1),
+            "attr2" => Ok(Field::Attribute2),
+            "attr3" => Ok(Field::Attribute3),
+            _ => Err(ParseQueryError(format!("unknown field \"{}\"", s))),
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    pub fn test(&self, lhs: f32, rhs: f32) -> bool {
+        match *self {
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::LessEqual => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterEqual => lhs >= rhs,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "=" | "==" => Ok(Comparison::Equal),
+            "!=" => Ok(Comparison::NotEqual),
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterEqual),
+            _ => Err(ParseQueryError(format!("unknown comparison \"{}\"", s))),
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Condition {
+    /// Object ID is one of the listed IDs.
+    ObjectIn(Vec<u16>),
+    /// Position lies inside the axis-aligned box spanned by two corners.
+    Within(Position, Position),
+    Compare(Field, Comparison, f32),
+}
+
+impl Condition {
+    pub fn matches(&self, obj: &SetObject) -> bool {
+        match *self {
+            Condition::ObjectIn(ref ids) => ids.contains(&obj.object.0),
+            Condition::Within(ref a, ref b) => {
+                let p = &obj.position;
+                p.x >= a.x.min(b.x) && p.x <= a.x.max(b.x) &&
+                    p.y >= a.y.min(b.y) && p.y <= a.y.max(b.y) &&
+                    p.z >= a.z.min(b.z) && p.z <= a.z.max(b.z)
+            }
+            Condition::Compare(field, comparison, value) => comparison.test(field.get(obj), value),
+        }
+    }
+}
+
+/// A conjunction of conditions. An empty filter matches every object.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Filter(pub Vec<Condition>);
+
+impl Filter {
+    pub fn matches(&self, obj: &SetObject) -> bool {
+        self.0.iter().all(|condition| condition.matches(obj))
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Transform {
+    Translate(Position),
+    /// Rotates about the vertical axis through the pivot by an angle in BAMS
+    /// (0x10000 is a full turn). The object's Y rotation is turned along with
+    /// its position.
+    Rotate {
+        pivot: Position,
+        angle: u16,
+    },
+    /// Scales positions away from the pivot. Rotations and attributes are
+    /// left alone.
+    Scale {
+        pivot: Position,
+        factor: f32,
+    },
+}
+
+impl Transform {
+    pub fn apply(&self, obj: &mut SetObject) {
+        match *self {
+            Transform::Translate(ref offset) => {
+                obj.position.x += offset.x;
+                obj.position.y += offset.y;
+                obj.position.z += offset.z;
+            }
+            Transform::Rotate { ref pivot, angle } => {
+                let theta = angle as f32 * 2.0 * PI / 65536.0;
+                let (sin, cos) = theta.sin_cos();
+                let dx = obj.position.x - pivot.x;
+                let dz = obj.position.z - pivot.z;
+
+                obj.position.x = pivot.x + dx * cos + dz * sin;
+                obj.position.z = pivot.z - dx * sin + dz * cos;
+                obj.rotation.y = obj.rotation.y.wrapping_add(angle);
+            }
+            Transform::Scale { ref pivot, factor } => {
+                obj.position.x = pivot.x + (obj.position.x - pivot.x) * factor;
+                obj.position.y = pivot.y + (obj.position.y - pivot.y) * factor;
+                obj.position.z = pivot.z + (obj.position.z - pivot.z) * factor;
+            }
+        }
+    }
+}
+
+/// A filter plus the transforms to run, in order, on every matching object.
+///
+/// The text form is `[CONDITION (and CONDITION)* :] TRANSFORM (then TRANSFORM)*`:
+///
+/// ```text
+/// id in 0x00,0x01 and within -100,0,-100 100,50,100 : translate 0,20,0
+/// y > 100 : rotate 90 about 0,0,0 then scale 2
+/// ```
+///
+/// Conditions are `id in ID,...`, `within X,Y,Z X,Y,Z` and `FIELD OP NUMBER`
+/// where `FIELD` is one of `id rx ry rz x y z attr1 attr2 attr3`. Transforms
+/// are `translate X,Y,Z`, `rotate DEGREES [about X,Y,Z]` and
+/// `scale FACTOR [about X,Y,Z]`.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Query {
+    pub filter: Filter,
+    pub transforms: Vec<Transform>,
+}
+
+impl Query {
+    /// Applies the query to `set_file`, returning how many objects matched.
+    pub fn apply(&self, set_file: &mut SetFile) -> usize {
+        let mut count = 0;
+        for obj in set_file.0.iter_mut().filter(|obj| self.filter.matches(obj)) {
+            for transform in self.transforms.iter() {
+                transform.apply(obj);
+            }
+            count += 1;
+        }
+        count
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s);
+        let split = tokens.iter().position(|t| t == ":");
+        let (filter_tokens, transform_tokens) = match split {
+            Some(idx) => (&tokens[..idx], &tokens[idx + 1..]),
+            None => (&tokens[..0], &tokens[..]),
+        };
+
+        let filter = Filter::from_tokens(filter_tokens)?;
+        let mut transforms = Vec::new();
+        for part in transform_tokens.split(|t| t == "then") {
+            transforms.push(Transform::from_tokens(part)?);
+        }
+
+        Ok(Query { filter, transforms })
+    }
+}
+
+impl Filter {
+    fn from_tokens(tokens: &[String]) -> Result<Filter, ParseQueryError> {
+        if tokens.is_empty() {
+            return Ok(Filter::default());
+        }
+
+        let mut conditions = Vec::new();
+        for part in tokens.split(|t| t == "and") {
+            conditions.push(Condition::from_tokens(part)?);
+        }
+        Ok(Filter(conditions))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Filter::from_tokens(&tokenize(s))
+    }
+}
+
+impl Condition {
+    fn from_tokens(tokens: &[String]) -> Result<Condition, ParseQueryError> {
+        let words: Vec<&str> = tokens.iter().map(|t| t.as_str()).collect();
+        match words.as_slice() {
+            ["id", "in", ids] => {
+                let ids = ids.split(',')
+                    .map(parse_id)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Condition::ObjectIn(ids))
+            }
+            ["within", a, b] => Ok(Condition::Within(parse_position(a)?, parse_position(b)?)),
+            ["id", op, id] => Ok(Condition::Compare(Field::Object, op.parse()?, parse_id(id)? as f32)),
+            [field, op, value] => Ok(Condition::Compare(field.parse()?, op.parse()?, parse_float(value)?)),
+            _ => Err(ParseQueryError(format!("bad condition \"{}\"", words.join(" ")))),
+        }
+    }
+}
+
+impl Transform {
+    fn from_tokens(tokens: &[String]) -> Result<Transform, ParseQueryError> {
+        let words: Vec<&str> = tokens.iter().map(|t| t.as_str()).collect();
+        match words.as_slice() {
+            ["translate", offset] => Ok(Transform::Translate(parse_position(offset)?)),
+            ["rotate", degrees] => Ok(Transform::Rotate { pivot: Position::default(), angle: degrees_to_bams(parse_float(degrees)?) }),
+            ["rotate", degrees, "about", pivot] => Ok(Transform::Rotate { pivot: parse_position(pivot)?, angle: degrees_to_bams(parse_float(degrees)?) }),
+            ["scale", factor] => Ok(Transform::Scale { pivot: Position::default(), factor: parse_float(factor)? }),
+            ["scale", factor, "about", pivot] => Ok(Transform::Scale { pivot: parse_position(pivot)?, factor: parse_float(factor)? }),
+            _ => Err(ParseQueryError(format!("bad transform \"{}\"", words.join(" ")))),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Transform::from_tokens(&tokenize(s))
+    }
+}
+
+pub fn degrees_to_bams(degrees: f32) -> u16 {
+    (degrees / 360.0 * 65536.0).round() as i64 as u16
+}
+
+pub fn bams_to_degrees(bams: u16) -> f32 {
+    bams as f32 * 360.0 / 65536.0
+}
+
+/// Splits on whitespace, and also around runs of comparison characters so
+/// `y>100` reads the same as `y > 100`. Lists stay one token however they're
+/// spaced, so `0x0A, 0x01` reads the same as `0x0A,0x01`.
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_op = false;
+
+    for c in s.chars() {
+        let is_op = "<>=!:".contains(c);
+        if (c.is_whitespace() || is_op != in_op) && !current.is_empty() {
+            push_token(&mut tokens, current.clone());
+            current.clear();
+        }
+        if !c.is_whitespace() {
+            current.push(c);
+            in_op = is_op;
+        }
+    }
+    if !current.is_empty() {
+        push_token(&mut tokens, current);
+    }
+    tokens
+}
+
+/// Adds `token` to `tokens`, joining it to the last one if a comma sits
+/// between them.
+fn push_token(tokens: &mut Vec<String>, token: String) {
+    match tokens.last_mut() {
+        Some(last) if last.ends_with(',') || token.starts_with(',') => last.push_str(&token),
+        _ => tokens.push(token),
+    }
+}
+
+fn parse_id(s: &str) -> Result<u16, ParseQueryError> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => u16::from_str(s),
+    };
+    result.map_err(|_| ParseQueryError(format!("bad object id \"{}\"", s)))
+}
+
+fn parse_float(s: &str) -> Result<f32, ParseQueryError> {
+    f32::from_str(s).map_err(|_| ParseQueryError(format!("bad number \"{}\"", s)))
+}
+
+fn parse_position(s: &str) -> Result<Position, ParseQueryError> {
+    let parts = s.split(',').map(parse_float).collect::<Result<Vec<_>, _>>()?;
+    match parts.as_slice() {
+        [x, y, z] => Ok(Position { x: *x, y: *y, z: *z }),
+        _ => Err(ParseQueryError(format!("bad position \"{}\" (expected X,Y,Z)", s))),
+    }
+}
+
+impl FromStr for Position {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_position(s)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct ParseQueryError(pub String);
+
+impl fmt::Display for ParseQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseQueryError {
+    fn description(&self) -> &str {
+        "ParseQueryError"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {SetFile, SetObject, Object, Position};
+
+    use super::{Query, Condition, Comparison, Field, Transform};
+
+    fn at(id: u16, x: f32, y: f32, z: f32) -> SetObject {
+        SetObject {
+            object: Object(id),
+            position: Position { x, y, z },
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn parse_query() {
+        let query: Query = "id in 0x00,0x01 and y>100 : translate 0,20,0 then rotate 90 about 1,2,3".parse().unwrap();
+        assert_eq!(query.filter.0, vec![
+            Condition::ObjectIn(vec![0, 1]),
+            Condition::Compare(Field::YPosition, Comparison::Greater, 100.0),
+        ]);
+        assert_eq!(query.transforms, vec![
+            Transform::Translate(Position { x: 0.0, y: 20.0, z: 0.0 }),
+            Transform::Rotate { pivot: Position { x: 1.0, y: 2.0, z: 3.0 }, angle: 0x4000 },
+        ]);
+
+        let spaced: Query = "id in 0x0A, 0x01 ,2 : translate 0, 20,0".parse().unwrap();
+        assert_eq!(spaced.filter.0, vec![Condition::ObjectIn(vec![0x0A, 0x01, 2])]);
+        assert_eq!(spaced.transforms, vec![Transform::Translate(Position { x: 0.0, y: 20.0, z: 0.0 })]);
+
+        assert!("translate 1,2".parse::<Query>().is_err());
+        assert!("q > 1 : scale 2".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn apply_to_matching_objects() {
+        let mut set_file = SetFile(vec![at(0, 0.0, 0.0, 0.0), at(0, 0.0, 200.0, 0.0), at(5, 0.0, 200.0, 0.0)]);
+        let query: Query = "id = 0 and within -10,100,-10 10,300,10 : translate 0,20,0".parse().unwrap();
+
+        assert_eq!(query.apply(&mut set_file), 1);
+        assert_eq!(set_file.0[0].position.y, 0.0);
+        assert_eq!(set_file.0[1].position.y, 220.0);
+        assert_eq!(set_file.0[2].position.y, 200.0);
+    }
+
+    #[test]
+    fn rotate_and_scale_about_pivot() {
+        let mut obj = at(0, 20.0, 0.0, 10.0);
+        Transform::Rotate { pivot: Position { x: 10.0, y: 0.0, z: 10.0 }, angle: 0x4000 }.apply(&mut obj);
+        assert!((obj.position.x - 10.0).abs() < 1e-4);
+        assert!((obj.position.z - 0.0).abs() < 1e-4);
+        assert_eq!(obj.rotation.y, 0x4000);
+
+        Transform::Scale { pivot: Position { x: 10.0, y: 0.0, z: 10.0 }, factor: 2.0 }.apply(&mut obj);
+        assert!((obj.position.z - -10.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,61 @@
+use std::error;
+use std::fmt;
+
+/// A string that couldn't be read from or written to its bytes, and where.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct TextError {
+    /// The string's index in its table, once it's known.
+    pub index: Option<usize>,
+    /// How far into the string's bytes the problem is. When writing, this is
+    /// how much had been written.
+    pub offset: usize,
+    pub kind: TextErrorKind,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum TextErrorKind {
+    /// A byte other than the text (0x07) or meta (0x0c) command where a
+    /// command should be.
+    BadCommand(u8),
+    /// A meta code's number doesn't fit in 32 bits.
+    BadNumber,
+    /// Japanese text that isn't valid Shift-JIS.
+    BadShiftJis,
+    /// A character the string's language can't encode: anything outside
+    /// Shift-JIS for Japanese, or outside Latin-1 otherwise.
+    Unencodable(char),
+}
+
+impl TextError {
+    pub fn new(offset: usize, kind: TextErrorKind) -> TextError {
+        TextError { index: None, offset, kind }
+    }
+
+    /// Attaches the index of the string the error is in.
+    pub fn in_string(mut self, index: usize) -> TextError {
+        self.index = Some(index);
+        self
+    }
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(index) = self.index {
+            write!(f, "string {}, ", index)?;
+        }
+        write!(f, "byte {}: {}", self.offset, self.kind)
+    }
+}
+
+impl fmt::Display for TextErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TextErrorKind::BadCommand(byte) => write!(f, "expected a text (0x07) or meta (0x0c) command, found 0x{:02x}", byte),
+            TextErrorKind::BadNumber => write!(f, "number is too large"),
+            TextErrorKind::BadShiftJis => write!(f, "text isn't valid Shift-JIS"),
+            TextErrorKind::Unencodable(c) => write!(f, "{:?} can't be encoded in this language", c),
+        }
+    }
+}
+
+impl error::Error for TextError {}
@@ -5,13 +5,15 @@ extern crate serde_derive;
 extern crate strable;
 
 use std::io::{self, Seek, Read, Write};
-use std::iter::Peekable;
-use std::slice;
 
 use encoding::{Encoding, EncoderTrap, DecoderTrap};
 use encoding::codec::japanese::Windows31JEncoding;
 use strable::Strable;
 
+mod error;
+
+pub use error::{TextError, TextErrorKind};
+
 #[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
 pub enum Language {
     Japanese,
@@ -21,19 +23,51 @@ pub enum Language {
     German,
 }
 
+/// A run of text, or a meta code changing how the game shows the message.
+/// Meta codes are written as a letter, some followed by a decimal number, in
+/// a run started by 0x0c and ended by a space.
+///
+/// Only the codes seen throughout the game's messages have variants; what
+/// the others do hasn't been worked out, so they're kept as `Unknown`.
 #[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
 pub enum TextElement {
+    /// `s`: the voice clip played with the message, by its number in the
+    /// voice bank.
     Sound(u32),
+    /// `w`: how long the message stays up before the next one.
     Wait(u32),
+    /// `D`: a flag with no number. Its effect isn't known.
     D,
+    /// Any other meta code, with the digits after it, written back exactly as
+    /// it was read.
+    Unknown(Vec<u8>),
     Text(String),
 }
 
 impl TextElement {
     pub fn is_meta(&self) -> bool {
+        !matches!(*self, TextElement::Text(_))
+    }
+
+    /// The letter a known meta code is written as, and its number if it has
+    /// one.
+    fn code(&self) -> Option<(u8, Option<u32>)> {
         match *self {
-            TextElement::Text(_) => false,
-            _ => true,
+            TextElement::Sound(num) => Some((b's', Some(num))),
+            TextElement::Wait(num) => Some((b'w', Some(num))),
+            TextElement::D => Some((b'D', None)),
+            TextElement::Unknown(_) | TextElement::Text(_) => None,
+        }
+    }
+
+    /// The meta code written as `code` followed by `num`, if it's a known one
+    /// written that way.
+    fn from_code(code: u8, num: Option<u32>) -> Option<TextElement> {
+        match (code, num) {
+            (b's', Some(num)) => Some(TextElement::Sound(num)),
+            (b'w', Some(num)) => Some(TextElement::Wait(num)),
+            (b'D', None) => Some(TextElement::D),
+            _ => None,
         }
     }
 }
@@ -42,7 +76,8 @@ impl TextElement {
 pub struct Sa2Text(pub Vec<TextElement>);
 
 impl Sa2Text {
-    pub fn to_bytes(&self, language: Language) -> Vec<u8> {
+    /// Encodes the string, failing on characters its language can't encode.
+    pub fn to_bytes(&self, language: Language) -> Result<Vec<u8>, TextError> {
         enum State {
             Text,
             Meta,
@@ -59,24 +94,19 @@ impl Sa2Text {
                     }
                     state = Some(State::Meta);
 
-                    match *e {
-                        TextElement::Sound(num) => {
-                            bytes.push('s' as u8);
-                            let num_string = num.to_string().into_bytes();
-                            bytes.extend_from_slice(&num_string);
-                        }
-                        TextElement::Wait(num) => {
-                            bytes.push('w' as u8);
+                    if let TextElement::Unknown(ref code) = *e {
+                        bytes.extend_from_slice(code);
+                    }
+                    else {
+                        let (code, num) = e.code().unwrap();
+                        bytes.push(code);
+                        if let Some(num) = num {
                             let num_string = num.to_string().into_bytes();
                             bytes.extend_from_slice(&num_string);
                         }
-                        TextElement::D => {
-                            bytes.push('D' as u8);
-                        }
-                        _ => unreachable!(),
                     }
                 }
-                &TextElement::Text(ref string) => {
+                TextElement::Text(ref string) => {
                     match state {
                         Some(State::Meta) => bytes.extend_from_slice(&[0x20, 0x07]),
                         None => bytes.push(0x07),
@@ -84,104 +114,111 @@ impl Sa2Text {
                     }
                     state = Some(State::Text);
 
-                    // XXX: Do some proper checks on codepoints and actual errors.
-                    match language {
-                        Language::Japanese => {
-                            let encoding = Windows31JEncoding;
-                            bytes.extend_from_slice(&encoding.encode(&string, EncoderTrap::Strict).unwrap());
-                        }
-                        _ => {
-                            bytes.extend(string.chars().map(|c| c as u32 as u8));
+                    for c in string.chars() {
+                        match language {
+                            // Japanese uses SHIFT JIS encoding
+                            Language::Japanese => {
+                                let encoding = Windows31JEncoding;
+                                let encoded = encoding.encode(c.encode_utf8(&mut [0; 4]), EncoderTrap::Strict)
+                                    .map_err(|_| TextError::new(bytes.len(), TextErrorKind::Unencodable(c)))?;
+                                bytes.extend_from_slice(&encoded);
+                            }
+                            // Everything else uses Latin1
+                            _ if (c as u32) <= 0xff => bytes.push(c as u32 as u8),
+                            _ => return Err(TextError::new(bytes.len(), TextErrorKind::Unencodable(c))),
                         }
                     }
                 }
                 _ => unreachable!(),
             }
         }
-        bytes
+        Ok(bytes)
     }
 
-    fn from_slice(slice: &[u8], language: Language) -> Sa2Text {
+    /// Decodes a string from its bytes, without the null terminator.
+    pub fn from_slice(slice: &[u8], language: Language) -> Result<Sa2Text, TextError> {
         let mut elements = Vec::new();
-        let mut peeker = slice.iter().peekable();
+        let mut pos = 0;
 
-        loop {
-            match peeker.next() {
-                Some(&0x0c) => {
-                    let mut meta_data = Sa2Text::read_meta(&mut peeker);
-                    elements.append(&mut meta_data);
+        while pos < slice.len() {
+            match slice[pos] {
+                0x0c => {
+                    pos = Sa2Text::read_meta(slice, pos + 1, &mut elements)?;
                 }
-                Some(&0x07) => {
-                    let text = TextElement::Text(Sa2Text::read_text(&mut peeker, language));
-                    elements.push(text);
+                0x07 => {
+                    let (text, end) = Sa2Text::read_text(slice, pos + 1, language)?;
+                    elements.push(TextElement::Text(text));
+                    pos = end;
                 }
-                None => return Sa2Text(elements),
-                _ => panic!("Bad command byte."),
+                byte => return Err(TextError::new(pos, TextErrorKind::BadCommand(byte))),
             }
         }
-    }
 
-    fn read_meta(peeker: &mut Peekable<slice::Iter<u8>>) -> Vec<TextElement> {
-        let mut elements = Vec::new();
+        Ok(Sa2Text(elements))
+    }
 
+    /// Reads meta codes starting at `pos` up to the space ending them, and
+    /// gives the position after it.
+    fn read_meta(slice: &[u8], mut pos: usize, elements: &mut Vec<TextElement>) -> Result<usize, TextError> {
         loop {
-            match peeker.next() {
-                // 's'
-                Some(&0x73) => {
-                    let num = Sa2Text::read_number(peeker);
-                    elements.push(TextElement::Sound(num));
-                }
-                // 'w'
-                Some(&0x77) => {
-                    let num = Sa2Text::read_number(peeker);
-                    elements.push(TextElement::Wait(num));
-                }
-                // 'D'
-                Some(&0x44) => {
-                    elements.push(TextElement::D);
+            match slice.get(pos) {
+                Some(&b' ') => return Ok(pos + 1),
+                None => return Ok(pos),
+                Some(&code) => {
+                    let end = slice[pos + 1..].iter()
+                        .position(|b| !b.is_ascii_digit())
+                        .map(|len| pos + 1 + len)
+                        .unwrap_or(slice.len());
+                    let digits = &slice[pos + 1..end];
+                    let element = if digits.is_empty() {
+                        TextElement::from_code(code, None)
+                    }
+                    else {
+                        match Sa2Text::read_number(digits) {
+                            Some(num) => TextElement::from_code(code, Some(num)),
+                            // Only codes known to take a number need it to fit.
+                            None if TextElement::from_code(code, Some(0)).is_some() => {
+                                return Err(TextError::new(pos + 1, TextErrorKind::BadNumber));
+                            }
+                            None => None,
+                        }
+                    };
+
+                    elements.push(element.unwrap_or_else(|| TextElement::Unknown(slice[pos..end].to_vec())));
+                    pos = end;
                 }
-                // ' '
-                Some(&0x20) | None => return elements,
-                _ => panic!("Bad meta byte."),
             }
         }
     }
 
-    fn read_number(peeker: &mut Peekable<slice::Iter<u8>>) -> u32 {
-        let mut num = 0;
-
-        loop {
-            match peeker.peek().map(|x| (**x as char).is_digit(10)) {
-                Some(true) => {
-                    num *= 10;
-                    num += (peeker.next().map(|x| *x).unwrap() as char).to_digit(10).unwrap();
-                }
-                _ => return num,
-            }
-        }
+    /// Parses `digits`, or gives `None` if the number doesn't fit.
+    fn read_number(digits: &[u8]) -> Option<u32> {
+        digits.iter().try_fold(0u32, |num, digit| num.checked_mul(10)?.checked_add((digit - b'0') as u32))
     }
 
-    fn read_text(peeker: &mut Peekable<slice::Iter<u8>>, language: Language) -> String {
-        let mut str_data = Vec::new();
-        loop {
-            match peeker.peek() {
-                Some(&&0x0c) | None => {
-                    match language {
-                        // Japanese uses SHIFT JIS encoding
-                        Language::Japanese => {
-                            let encoding = Windows31JEncoding;
-                            return encoding.decode(&str_data, DecoderTrap::Strict).unwrap();
-                        }
-                        // Everything else uses Latin1
-                        _ => {
-                            // Fancy stuff because Latin1 and UTF-8 codepoints match up
-                            return str_data.into_iter().map(|c| c as char).collect();
-                        }
-                    }
-                }
-                Some(_) => str_data.push(*peeker.next().unwrap()),
+    /// Reads text starting at `start` up to the next meta command, and gives
+    /// the position after it.
+    fn read_text(slice: &[u8], start: usize, language: Language) -> Result<(String, usize), TextError> {
+        let end = slice[start..].iter()
+            .position(|b| *b == 0x0c)
+            .map(|len| start + len)
+            .unwrap_or(slice.len());
+        let str_data = &slice[start..end];
+
+        let text = match language {
+            // Japanese uses SHIFT JIS encoding
+            Language::Japanese => {
+                let encoding = Windows31JEncoding;
+                encoding.decode(str_data, DecoderTrap::Strict)
+                    .map_err(|_| TextError::new(start, TextErrorKind::BadShiftJis))?
             }
-        }
+            // Everything else uses Latin1
+            _ => {
+                // Fancy stuff because Latin1 and UTF-8 codepoints match up
+                str_data.iter().map(|c| *c as char).collect()
+            }
+        };
+        Ok((text, end))
     }
 }
 
@@ -192,42 +229,123 @@ pub struct Sa2TextTable {
 }
 
 impl Sa2TextTable {
-
+    /// Reads a table of strings. A string that can't be decoded fails with
+    /// an `InvalidData` error wrapping a `TextError` that says which one.
     pub fn from_seek<S>(mut seekable: S, language: Language) -> io::Result<Sa2TextTable>
         where S: Read + Seek
     {
         let string_table = Strable::from_seek(&mut seekable)?;
 
         let mut texts = Vec::new();
-        for byte_string in string_table.data_table {
-            let text = Sa2Text::from_slice(&byte_string, language);
+        for (index, byte_string) in string_table.data_table.iter().enumerate() {
+            let text = Sa2Text::from_slice(byte_string, language)
+                .map_err(|e| invalid_data(e.in_string(index)))?;
             texts.push(text);
         }
 
         Ok(
             Sa2TextTable {
-                language: language,
-                texts: texts,
+                language,
+                texts,
             }
         )
     }
 
+    /// Writes the table of strings. A string that can't be encoded fails
+    /// the same way as in `from_seek`.
     pub fn to_writer<W>(&self, writer: W) -> io::Result<()>
         where W: Write
     {
-        let data_table = self.texts.iter()
-            .map(|t| t.to_bytes(self.language))
-            .collect();
+        let mut data_table = Vec::with_capacity(self.texts.len());
+        for (index, text) in self.texts.iter().enumerate() {
+            let bytes = text.to_bytes(self.language)
+                .map_err(|e| invalid_data(e.in_string(index)))?;
+            data_table.push(bytes);
+        }
         let string_table = Strable {
-            data_table: data_table,
+            data_table,
         };
         string_table.to_writer(writer)
     }
 }
 
+fn invalid_data(err: TextError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+
+    use strable::Strable;
+    use super::{Language, Sa2Text, Sa2TextTable, TextElement, TextError, TextErrorKind};
+
     #[test]
     fn it_works() {
     }
+
+    #[test]
+    fn round_trips_strings() {
+        let bytes = b"\x0cs12w30D \x07Hello\x0cw5 \x07World";
+        let text = Sa2Text::from_slice(bytes, Language::English).unwrap();
+        assert_eq!(text.0, vec![
+            TextElement::Sound(12),
+            TextElement::Wait(30),
+            TextElement::D,
+            TextElement::Text("Hello".to_string()),
+            TextElement::Wait(5),
+            TextElement::Text("World".to_string()),
+        ]);
+        assert_eq!(text.to_bytes(Language::English).unwrap(), bytes.to_vec());
+    }
+
+    #[test]
+    fn keeps_unknown_meta_codes() {
+        let bytes = b"\x0cx12w5Q\x0cD9t2c3vl15 \x07Hi";
+        let text = Sa2Text::from_slice(bytes, Language::English).unwrap();
+        assert_eq!(text.0, vec![
+            TextElement::Unknown(b"x12".to_vec()),
+            TextElement::Wait(5),
+            TextElement::Unknown(b"Q".to_vec()),
+            TextElement::Unknown(b"\x0c".to_vec()),
+            TextElement::Unknown(b"D9".to_vec()),
+            TextElement::Unknown(b"t2".to_vec()),
+            TextElement::Unknown(b"c3".to_vec()),
+            TextElement::Unknown(b"v".to_vec()),
+            TextElement::Unknown(b"l15".to_vec()),
+            TextElement::Text("Hi".to_string()),
+        ]);
+        assert_eq!(text.to_bytes(Language::English).unwrap(), bytes.to_vec());
+
+        let bytes = b"\x0cx99999999999 \x07Hi";
+        let text = Sa2Text::from_slice(bytes, Language::English).unwrap();
+        assert_eq!(text.0[0], TextElement::Unknown(b"x99999999999".to_vec()));
+        assert_eq!(text.to_bytes(Language::English).unwrap(), bytes.to_vec());
+    }
+
+    #[test]
+    fn reports_where_strings_are_bad() {
+        let err = Sa2Text::from_slice(b"\x07Hi\x0cs1 x", Language::English).unwrap_err();
+        assert_eq!(err, TextError::new(7, TextErrorKind::BadCommand(b'x')));
+        let err = Sa2Text::from_slice(b"\x0cw99999999999 ", Language::English).unwrap_err();
+        assert_eq!(err.kind, TextErrorKind::BadNumber);
+        let err = Sa2Text::from_slice(b"\x07\x81", Language::Japanese).unwrap_err();
+        assert_eq!(err, TextError::new(1, TextErrorKind::BadShiftJis));
+
+        let text = Sa2Text(vec![TextElement::Text("\u{3042}".to_string())]);
+        assert_eq!(text.to_bytes(Language::English).unwrap_err(), TextError::new(1, TextErrorKind::Unencodable('\u{3042}')));
+        assert_eq!(text.to_bytes(Language::Japanese).unwrap(), vec![0x07, 0x82, 0xa0]);
+    }
+
+    #[test]
+    fn tables_say_which_string_failed() {
+        let strable = Strable { data_table: vec![b"\x07Fine".to_vec(), b"oops".to_vec()] };
+        let mut data = Vec::new();
+        strable.to_writer(&mut data).unwrap();
+
+        let err = Sa2TextTable::from_seek(Cursor::new(data), Language::English).unwrap_err();
+        let text_err = err.get_ref().and_then(|e| e.downcast_ref::<TextError>()).unwrap();
+        assert_eq!(text_err.index, Some(1));
+        assert_eq!(err.to_string(), "string 1, byte 0: expected a text (0x07) or meta (0x0c) command, found 0x6f");
+    }
 }
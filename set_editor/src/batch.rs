@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use error::{self, Error, ResultExt};
+use format::Format;
+
+/// Whether a batch job turns a set file into text or the other way round.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Action {
+    Decode,
+    Encode,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Job {
+    pub action: Action,
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+#[derive(Debug,Default)]
+pub struct Summary {
+    pub decoded: usize,
+    pub encoded: usize,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+/// Finds every file to convert under `inputs`. Set files named like
+/// `set*.bin` are decoded to `format`, and files with `format`'s extension
+/// are encoded, as `.prs` when `compress` is set. Files given directly go
+/// straight into `out_dir`; files found in a directory keep their path
+/// relative to it.
+pub fn collect_jobs(inputs: &[PathBuf], out_dir: &Path, format: Format, compress: bool) -> error::Result<Vec<Job>> {
+    let mut jobs = Vec::new();
+    for input in inputs.iter() {
+        if input.is_dir() {
+            for entry in WalkDir::new(input).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+                let entry = entry.map_err(io::Error::from).in_file(input)?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative = entry.path().strip_prefix(input).unwrap_or_else(|_| entry.path());
+                if let Some(job) = job_for(entry.path(), &out_dir.join(relative), format, compress) {
+                    jobs.push(job);
+                }
+            }
+        }
+        else {
+            let file_name = input.file_name().map(PathBuf::from).unwrap_or_default();
+            match job_for(input, &out_dir.join(file_name), format, compress) {
+                Some(job) => jobs.push(job),
+                None => return Err(Error::other(format!("not a set*.bin or .{} file", format.extension())).in_file(input)),
+            }
+        }
+    }
+    Ok(jobs)
+}
+
+fn job_for(input: &Path, output: &Path, format: Format, compress: bool) -> Option<Job> {
+    let file_name = input.file_name()?.to_str()?.to_lowercase();
+    let extension = input.extension()?.to_str()?.to_lowercase();
+
+    if extension == "bin" && file_name.starts_with("set") {
+        Some(Job { action: Action::Decode, input: input.to_path_buf(), output: output.with_extension(format.extension()) })
+    }
+    else if extension == format.extension() {
+        Some(Job { action: Action::Encode, input: input.to_path_buf(), output: output.with_extension(if compress { "prs" } else { "bin" }) })
+    }
+    else {
+        None
+    }
+}
+
+/// Runs `convert` on every job in parallel, creating output directories as
+/// needed, and tallies the results.
+pub fn run<F>(jobs: &[Job], convert: F) -> Summary
+    where F: Fn(&Job) -> error::Result<()> + Sync
+{
+    let summary = Mutex::new(Summary::default());
+
+    jobs.par_iter().for_each(|job| {
+        let result = match job.output.parent() {
+            Some(parent) => fs::create_dir_all(parent).in_file(parent),
+            None => Ok(()),
+        }.and_then(|_| convert(job));
+
+        let mut summary = summary.lock().unwrap();
+        match result {
+            Ok(_) if job.action == Action::Decode => summary.decoded += 1,
+            Ok(_) => summary.encoded += 1,
+            Err(e) => summary.failed.push((job.input.clone(), e)),
+        }
+    });
+
+    let mut summary = summary.into_inner().unwrap();
+    summary.failed.sort_by(|a, b| a.0.cmp(&b.0));
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    use format::Format;
+    use super::{collect_jobs, Action};
+
+    #[test]
+    fn mirrors_directories() {
+        let root = env::temp_dir().join(format!("set_editor_batch_{}", ::std::process::id()));
+        fs::create_dir_all(root.join("stage13")).unwrap();
+        for name in ["stage13/set0013_s.bin", "stage13/notes.txt", "stage13/other.bin", "set0016_u.json"].iter() {
+            File::create(root.join(name)).unwrap();
+        }
+
+        let out = PathBuf::from("out");
+        let jobs = collect_jobs(::std::slice::from_ref(&root), &out, Format::Json, false).unwrap();
+        let compressed = collect_jobs(::std::slice::from_ref(&root), &out, Format::Json, true).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].action, Action::Encode);
+        assert_eq!(jobs[0].output, out.join("set0016_u.bin"));
+        assert_eq!(jobs[1].action, Action::Decode);
+        assert_eq!(jobs[1].output, out.join("stage13").join("set0013_s.json"));
+        assert_eq!(compressed[0].output, out.join("set0016_u.prs"));
+        assert_eq!(compressed[1].output, jobs[1].output);
+    }
+}
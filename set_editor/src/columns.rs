@@ -0,0 +1,349 @@
+use sa2_set::{SetObject, Object};
+
+use obj_table::ObjectTable;
+
+/// A value of a set object the grid can show in a column.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnField {
+    Index,
+    ObjectId,
+    ObjectName,
+    XRotation,
+    YRotation,
+    ZRotation,
+    XPosition,
+    YPosition,
+    ZPosition,
+    Attribute1,
+    Attribute2,
+    Attribute3,
+}
+
+/// Every field, in the order the grid's store keeps them.
+pub const FIELDS: [ColumnField; 12] = [
+    ColumnField::Index,
+    ColumnField::ObjectId,
+    ColumnField::ObjectName,
+    ColumnField::XRotation,
+    ColumnField::YRotation,
+    ColumnField::ZRotation,
+    ColumnField::XPosition,
+    ColumnField::YPosition,
+    ColumnField::ZPosition,
+    ColumnField::Attribute1,
+    ColumnField::Attribute2,
+    ColumnField::Attribute3,
+];
+
+/// The store column holding each row's object key, after the text and sort
+/// value of every field.
+pub const KEY_COLUMN: i32 = 2 * FIELDS.len() as i32;
+
+/// How a column writes its field's value.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnFormat {
+    /// Whole numbers in hexadecimal, as the game's tables list them.
+    Hex,
+    /// Whole numbers in decimal.
+    Decimal,
+    /// Rotations in degrees instead of the game's 65536ths of a turn.
+    Degrees,
+    Float,
+}
+
+const ROTATION_FORMATS: &[ColumnFormat] = &[ColumnFormat::Hex, ColumnFormat::Decimal, ColumnFormat::Degrees];
+const ID_FORMATS: &[ColumnFormat] = &[ColumnFormat::Hex, ColumnFormat::Decimal];
+const ATTRIBUTE_FORMATS: &[ColumnFormat] = &[ColumnFormat::Float, ColumnFormat::Decimal, ColumnFormat::Hex];
+const POSITION_FORMATS: &[ColumnFormat] = &[ColumnFormat::Float];
+const INDEX_FORMATS: &[ColumnFormat] = &[ColumnFormat::Decimal];
+const NAME_FORMATS: &[ColumnFormat] = &[];
+
+impl ColumnField {
+    pub fn title(self) -> &'static str {
+        match self {
+            ColumnField::Index => "Index",
+            ColumnField::ObjectId => "Object ID",
+            ColumnField::ObjectName => "Object Name",
+            ColumnField::XRotation => "X Rotation",
+            ColumnField::YRotation => "Y Rotation",
+            ColumnField::ZRotation => "Z Rotation",
+            ColumnField::XPosition => "X Position",
+            ColumnField::YPosition => "Y Position",
+            ColumnField::ZPosition => "Z Position",
+            ColumnField::Attribute1 => "Attribute 1",
+            ColumnField::Attribute2 => "Attribute 2",
+            ColumnField::Attribute3 => "Attribute 3",
+        }
+    }
+
+    /// The store column with the field's text as shown.
+    pub fn text_column(self) -> i32 {
+        FIELDS.iter().position(|&field| field == self).unwrap() as i32
+    }
+
+    /// The store column the grid sorts the field by: its number where it has
+    /// one, so decimal and degree columns don't sort as text.
+    pub fn sort_column(self) -> i32 {
+        match self {
+            ColumnField::ObjectName => self.text_column(),
+            _ => FIELDS.len() as i32 + self.text_column(),
+        }
+    }
+
+    /// The field sorted by store column `column`, as the grid's columns are
+    /// told apart once they've been reordered.
+    pub fn from_sort_column(column: i32) -> Option<ColumnField> {
+        FIELDS.iter().cloned().find(|field| field.sort_column() == column)
+    }
+
+    /// The formats the field can be shown in, the first being its default.
+    pub fn formats(self) -> &'static [ColumnFormat] {
+        match self {
+            ColumnField::Index => INDEX_FORMATS,
+            ColumnField::ObjectId => ID_FORMATS,
+            ColumnField::ObjectName => NAME_FORMATS,
+            ColumnField::XRotation | ColumnField::YRotation | ColumnField::ZRotation => ROTATION_FORMATS,
+            ColumnField::XPosition | ColumnField::YPosition | ColumnField::ZPosition => POSITION_FORMATS,
+            ColumnField::Attribute1 | ColumnField::Attribute2 | ColumnField::Attribute3 => ATTRIBUTE_FORMATS,
+        }
+    }
+
+    /// Whether the field is part of the object, rather than worked out from it.
+    pub fn editable(self) -> bool {
+        !matches!(self, ColumnField::Index | ColumnField::ObjectName)
+    }
+
+    /// The field's value in object `obj` at `index`. Names have none.
+    pub fn value(self, index: u32, obj: &SetObject) -> f64 {
+        match self {
+            ColumnField::Index => index as f64,
+            ColumnField::ObjectId => obj.object.0 as f64,
+            ColumnField::ObjectName => 0.0,
+            ColumnField::XRotation => obj.rotation.x as f64,
+            ColumnField::YRotation => obj.rotation.y as f64,
+            ColumnField::ZRotation => obj.rotation.z as f64,
+            ColumnField::XPosition => obj.position.x as f64,
+            ColumnField::YPosition => obj.position.y as f64,
+            ColumnField::ZPosition => obj.position.z as f64,
+            ColumnField::Attribute1 => obj.attr1 as f64,
+            ColumnField::Attribute2 => obj.attr2 as f64,
+            ColumnField::Attribute3 => obj.attr3 as f64,
+        }
+    }
+
+    /// Sets the field in `obj`, failing if the value doesn't fit it.
+    pub fn set(self, obj: &mut SetObject, value: f64) -> Result<(), String> {
+        let whole = || {
+            if value.fract() == 0.0 && value >= 0.0 && value <= u16::MAX as f64 {
+                Ok(value as u16)
+            }
+            else {
+                Err(format!("{} must be a whole number from 0 to FFFF", self.title()))
+            }
+        };
+
+        match self {
+            ColumnField::Index | ColumnField::ObjectName => return Err(format!("{} can't be edited", self.title())),
+            ColumnField::ObjectId => obj.object = Object(whole()?),
+            ColumnField::XRotation => obj.rotation.x = whole()?,
+            ColumnField::YRotation => obj.rotation.y = whole()?,
+            ColumnField::ZRotation => obj.rotation.z = whole()?,
+            ColumnField::XPosition => obj.position.x = value as f32,
+            ColumnField::YPosition => obj.position.y = value as f32,
+            ColumnField::ZPosition => obj.position.z = value as f32,
+            ColumnField::Attribute1 => obj.attr1 = value as f32,
+            ColumnField::Attribute2 => obj.attr2 = value as f32,
+            ColumnField::Attribute3 => obj.attr3 = value as f32,
+        }
+        Ok(())
+    }
+}
+
+impl ColumnFormat {
+    pub fn title(self) -> &'static str {
+        match self {
+            ColumnFormat::Hex => "Hexadecimal",
+            ColumnFormat::Decimal => "Decimal",
+            ColumnFormat::Degrees => "Degrees",
+            ColumnFormat::Float => "Exact",
+        }
+    }
+
+    pub fn show(self, value: f64) -> String {
+        match self {
+            ColumnFormat::Hex if value < 0.0 => format!("-{:04X}", -value as i64),
+            ColumnFormat::Hex => format!("{:04X}", value as i64),
+            ColumnFormat::Decimal => format!("{}", value as i64),
+            ColumnFormat::Degrees => format!("{:.2}", value * 360.0 / 65536.0),
+            // Values come from f32s, so they're shown as such rather than
+            // with the digits widening them to f64 adds.
+            ColumnFormat::Float => format!("{}", value as f32),
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<f64, String> {
+        let text = text.trim();
+        match self {
+            ColumnFormat::Hex => {
+                let (negative, digits) = match text.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, text),
+                };
+                let digits = digits.trim_start_matches("0x").trim_start_matches("0X");
+                i64::from_str_radix(digits, 16)
+                    .map(|value| if negative { -value as f64 } else { value as f64 })
+                    .map_err(|_| format!("\"{}\" isn't a hexadecimal number", text))
+            }
+            ColumnFormat::Decimal => text.parse::<i64>()
+                .map(|value| value as f64)
+                .map_err(|_| format!("\"{}\" isn't a whole number", text)),
+            ColumnFormat::Degrees => text.parse::<f64>()
+                // Any angle works, turned into the one turn the game keeps.
+                .map(|degrees| ((degrees / 360.0 * 65536.0).round() as i64).rem_euclid(65536) as f64)
+                .map_err(|_| format!("\"{}\" isn't a number of degrees", text)),
+            ColumnFormat::Float => text.parse::<f32>()
+                .map(|value| value as f64)
+                .map_err(|_| format!("\"{}\" isn't a number", text)),
+        }
+    }
+}
+
+/// One of the grid's columns.
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub struct Column {
+    pub field: ColumnField,
+    /// Ignored for fields without formats.
+    pub format: ColumnFormat,
+    pub visible: bool,
+    /// Width in pixels, or 0 to fit the contents.
+    #[serde(default)]
+    pub width: i32,
+}
+
+impl Column {
+    pub fn new(field: ColumnField) -> Column {
+        Column {
+            field,
+            format: field.formats().first().cloned().unwrap_or(ColumnFormat::Float),
+            visible: true,
+            width: 0,
+        }
+    }
+
+    /// The field of `obj` as this column shows it.
+    pub fn text(&self, index: u32, obj: &SetObject, names: Option<(&ObjectTable, u16)>) -> String {
+        match self.field {
+            ColumnField::ObjectName => names
+                .and_then(|(table, level)| table.lookup(level, obj.object.0))
+                .cloned()
+                .unwrap_or_default(),
+            field => self.format.show(field.value(index, obj)),
+        }
+    }
+
+    /// Sets this column's field in `obj` from text written in its format.
+    pub fn edit(&self, obj: &mut SetObject, text: &str) -> Result<(), String> {
+        let value = self.format.parse(text)?;
+        self.field.set(obj, value)
+    }
+}
+
+/// The grid's columns in the order they're shown, each field once.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct ColumnLayout(pub Vec<Column>);
+
+impl Default for ColumnLayout {
+    fn default() -> ColumnLayout {
+        ColumnLayout(FIELDS.iter().map(|&field| Column::new(field)).collect())
+    }
+}
+
+impl ColumnLayout {
+    /// Drops repeated fields and formats a field can't use, and adds missing
+    /// fields at the end, so a layout read from a file describes every field.
+    pub fn normalized(self) -> ColumnLayout {
+        let mut columns: Vec<Column> = Vec::new();
+        for mut column in self.0 {
+            if columns.iter().any(|known| known.field == column.field) {
+                continue;
+            }
+            if !column.field.formats().contains(&column.format) {
+                column.format = Column::new(column.field).format;
+            }
+            columns.push(column);
+        }
+        for &field in FIELDS.iter() {
+            if !columns.iter().any(|column| column.field == field) {
+                columns.push(Column::new(field));
+            }
+        }
+        ColumnLayout(columns)
+    }
+
+    pub fn column(&self, field: ColumnField) -> Column {
+        self.0.iter().find(|column| column.field == field).cloned().unwrap_or_else(|| Column::new(field))
+    }
+
+    pub fn column_mut(&mut self, field: ColumnField) -> Option<&mut Column> {
+        self.0.iter_mut().find(|column| column.field == field)
+    }
+
+    /// Puts the columns in `order`, leaving fields it doesn't mention after
+    /// the ones it does.
+    pub fn reorder(&mut self, order: &[ColumnField]) {
+        let position = |field: ColumnField| order.iter().position(|&f| f == field).unwrap_or(order.len());
+        self.0.sort_by_key(|column| position(column.field));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::SetObject;
+    use super::{Column, ColumnField, ColumnFormat, ColumnLayout, FIELDS};
+
+    #[test]
+    fn shows_and_edits_in_each_format() {
+        let mut obj = SetObject::default();
+        obj.rotation.y = 0x4000;
+
+        let mut column = Column::new(ColumnField::YRotation);
+        assert_eq!(column.text(0, &obj, None), "4000");
+        column.format = ColumnFormat::Degrees;
+        assert_eq!(column.text(0, &obj, None), "90.00");
+        column.edit(&mut obj, "-90").unwrap();
+        assert_eq!(obj.rotation.y, 0xC000);
+        column.format = ColumnFormat::Decimal;
+        assert!(column.edit(&mut obj, "70000").is_err());
+        assert_eq!(obj.rotation.y, 0xC000);
+
+        let attr = Column { format: ColumnFormat::Hex, ..Column::new(ColumnField::Attribute2) };
+        attr.edit(&mut obj, "0x1F").unwrap();
+        assert_eq!(obj.attr2, 31.0);
+        assert!(Column::new(ColumnField::Index).edit(&mut obj, "3").is_err());
+    }
+
+    #[test]
+    fn normalizes_layouts() {
+        let layout = ColumnLayout(vec![
+            Column { visible: false, ..Column::new(ColumnField::Attribute1) },
+            Column::new(ColumnField::Index),
+            Column { format: ColumnFormat::Degrees, ..Column::new(ColumnField::XPosition) },
+            Column::new(ColumnField::Attribute1),
+        ]).normalized();
+
+        assert_eq!(layout.0.len(), FIELDS.len());
+        assert_eq!(layout.0[0].field, ColumnField::Attribute1);
+        assert!(!layout.0[0].visible);
+        assert_eq!(layout.0[2].format, ColumnFormat::Float);
+
+        let mut layout = ColumnLayout::default();
+        layout.reorder(&[ColumnField::ObjectName, ColumnField::Index]);
+        assert_eq!(layout.0[0].field, ColumnField::ObjectName);
+        assert_eq!(layout.0[2].field, ColumnField::ObjectId);
+        for &field in FIELDS.iter() {
+            assert_eq!(ColumnField::from_sort_column(field.sort_column()), Some(field));
+        }
+    }
+}
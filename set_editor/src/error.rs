@@ -0,0 +1,171 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
+
+use csv;
+use serde_json;
+use toml;
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error from reading or writing one of set_editor's files, along with the
+/// file it happened in when that's known.
+#[derive(Debug)]
+pub struct Error {
+    path: Option<PathBuf>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    /// The set file ended early or couldn't be read.
+    SetFile(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    TomlRead(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+    /// An object whose fields parsed but don't make sense, such as an
+    /// unknown object name. Holds the object's index.
+    Object(usize, String),
+    Other(String),
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error { path: None, kind }
+    }
+
+    pub fn set_file(err: io::Error) -> Error {
+        Error::new(ErrorKind::SetFile(err))
+    }
+
+    pub fn object(index: usize, message: String) -> Error {
+        Error::new(ErrorKind::Object(index, message))
+    }
+
+    pub fn other<S>(message: S) -> Error
+        where S: Into<String>
+    {
+        Error::new(ErrorKind::Other(message.into()))
+    }
+
+    /// Attaches the file the error happened in, unless it already has one.
+    pub fn in_file(mut self, path: &Path) -> Error {
+        if self.path.is_none() {
+            self.path = Some(path.to_path_buf());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref path) = self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Io(ref e) => write!(f, "{}", e),
+            ErrorKind::SetFile(ref e) => write!(f, "not a valid set file ({})", e),
+            ErrorKind::Json(ref e) => {
+                // serde_json puts the position at the end; move it to the
+                // front so it reads like the other formats.
+                let message = e.to_string();
+                let message = message.rsplit_once(" at line ").map(|(message, _)| message).unwrap_or(&message);
+                if e.line() > 0 {
+                    write!(f, "line {} column {}: {}", e.line(), e.column(), message)
+                }
+                else {
+                    write!(f, "{}", message)
+                }
+            }
+            ErrorKind::Csv(ref e) => write!(f, "{}", e),
+            ErrorKind::TomlRead(ref e) => write!(f, "{}", e),
+            ErrorKind::TomlWrite(ref e) => write!(f, "{}", e),
+            ErrorKind::Object(index, ref message) => write!(f, "object {}: {}", index, message),
+            ErrorKind::Other(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref e) | ErrorKind::SetFile(ref e) => Some(e),
+            ErrorKind::Json(ref e) => Some(e),
+            ErrorKind::Csv(ref e) => Some(e),
+            ErrorKind::TomlRead(ref e) => Some(e),
+            ErrorKind::TomlWrite(ref e) => Some(e),
+            ErrorKind::Object(..) | ErrorKind::Other(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new(ErrorKind::Io(err))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::new(ErrorKind::Json(err))
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Error {
+        Error::new(ErrorKind::Csv(err))
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Error {
+        Error::new(ErrorKind::TomlRead(err))
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Error {
+        Error::new(ErrorKind::TomlWrite(err))
+    }
+}
+
+/// Adds `in_file` to results so a path can be attached with `?`-friendly
+/// chaining.
+pub trait ResultExt<T> {
+    fn in_file(self, path: &Path) -> Result<T>;
+}
+
+impl<T, E> ResultExt<T> for result::Result<T, E>
+    where E: Into<Error>
+{
+    fn in_file(self, path: &Path) -> Result<T> {
+        self.map_err(|e| e.into().in_file(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json;
+
+    use super::{Error, ResultExt};
+
+    #[test]
+    fn json_errors_lead_with_position() {
+        let result: Result<Vec<f32>, Error> = serde_json::from_str::<Vec<f32>>("[1.0,\n  \"x\"]")
+            .in_file(Path::new("set0013_s.json"));
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "set0013_s.json: line 2 column 5: invalid type: string \"x\", expected f32");
+    }
+}
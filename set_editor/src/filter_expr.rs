@@ -0,0 +1,344 @@
+use std::str::FromStr;
+
+use sa2_set::SetObject;
+use sa2_set::transform::{Field, Comparison, ParseQueryError};
+
+use obj_table::ObjectTable;
+
+/// A test over set objects, typed in the GUI's filter bar.
+///
+/// Conditions are `FIELD OP VALUE` and `FIELD in (VALUE, ...)`, where
+/// `FIELD` is `name` or one of the fields transform queries use (`id rx ry
+/// rz x y z attr1 attr2 attr3`). Numbers can be written in hex with `0x`.
+/// Names are matched without regard to case, `*` and `?` work as in file
+/// globs, and quotes allow spaces. Conditions combine with `&&`, `||` and
+/// `!` (or `and`, `or` and `not`) and parentheses:
+///
+/// ```text
+/// name=RING* && y > 100
+/// id in (0x0A,0x0B) || !(name = "E KUMI")
+/// ```
+#[derive(Clone,Debug,PartialEq)]
+pub enum FilterExpr {
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+    Compare(Field, Comparison, f32),
+    In(Field, Vec<f32>),
+    /// The object's name in the object table matches one of the patterns.
+    Name(Vec<String>),
+}
+
+impl FilterExpr {
+    /// Whether `obj` passes. Objects the table has no name for have an empty
+    /// one.
+    pub fn matches(&self, obj: &SetObject, names: Option<(&ObjectTable, u16)>) -> bool {
+        match *self {
+            FilterExpr::And(ref a, ref b) => a.matches(obj, names) && b.matches(obj, names),
+            FilterExpr::Or(ref a, ref b) => a.matches(obj, names) || b.matches(obj, names),
+            FilterExpr::Not(ref a) => !a.matches(obj, names),
+            FilterExpr::Compare(field, comparison, value) => comparison.test(field.get(obj), value),
+            FilterExpr::In(field, ref values) => values.contains(&field.get(obj)),
+            FilterExpr::Name(ref patterns) => {
+                let name = names.and_then(|(table, level)| table.lookup(level, obj.object.0)).map(|name| name.as_str()).unwrap_or("");
+                patterns.iter().any(|pattern| glob_matches(pattern, name))
+            }
+        }
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = ParseQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { tokens: tokenize(s)?, pos: 0 };
+        let expr = parser.or()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => Err(ParseQueryError(format!("unexpected {}", token))),
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(String),
+    Open,
+    Close,
+    Comma,
+}
+
+impl ::std::fmt::Display for Token {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            Token::Word(ref word) | Token::Quoted(ref word) | Token::Op(ref word) => write!(f, "\"{}\"", word),
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+            Token::Comma => write!(f, "\",\""),
+        }
+    }
+}
+
+/// Splits into words, quoted strings, parentheses, commas and runs of
+/// operator characters, so `y>100` reads the same as `y > 100`.
+fn tokenize(s: &str) -> Result<Vec<Token>, ParseQueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        }
+        else if c == '(' || c == ')' || c == ',' {
+            chars.next();
+            tokens.push(match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Comma,
+            });
+        }
+        else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => text.push(c),
+                    None => return Err(ParseQueryError(String::from("unterminated quote"))),
+                }
+            }
+            tokens.push(Token::Quoted(text));
+        }
+        else {
+            let is_op = |c: char| "<>=!&|".contains(c);
+            let op = is_op(c);
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || "(),\"".contains(c) || is_op(c) != op {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(if op { Token::Op(word) } else { Token::Word(word) });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Takes the next token if it's one of `ops`, written as an operator
+    /// or a word.
+    fn eat(&mut self, ops: &[&str]) -> bool {
+        let found = match self.peek() {
+            Some(&Token::Op(ref op)) | Some(&Token::Word(ref op)) => ops.iter().any(|o| op.eq_ignore_ascii_case(o)),
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<FilterExpr, ParseQueryError> {
+        let mut expr = self.and()?;
+        while self.eat(&["||", "or"]) {
+            expr = FilterExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<FilterExpr, ParseQueryError> {
+        let mut expr = self.unary()?;
+        while self.eat(&["&&", "and"]) {
+            expr = FilterExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<FilterExpr, ParseQueryError> {
+        if self.eat(&["!", "not"]) {
+            return Ok(FilterExpr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let expr = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err(ParseQueryError(String::from("missing \")\""))),
+            };
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<FilterExpr, ParseQueryError> {
+        let field = match self.next() {
+            Some(Token::Word(word)) => word,
+            Some(token) => return Err(ParseQueryError(format!("expected a field, found {}", token))),
+            None => return Err(ParseQueryError(String::from("expected a condition"))),
+        };
+
+        if self.eat(&["in"]) {
+            let values = self.list()?;
+            return if field == "name" {
+                Ok(FilterExpr::Name(values))
+            }
+            else {
+                let field = field.parse()?;
+                Ok(FilterExpr::In(field, values.iter().map(|value| parse_number(value)).collect::<Result<_, _>>()?))
+            };
+        }
+
+        let comparison: Comparison = match self.next() {
+            Some(Token::Op(op)) => op.parse()?,
+            _ => return Err(ParseQueryError(format!("expected a comparison after \"{}\"", field))),
+        };
+        let value = self.value()?;
+        if field == "name" {
+            match comparison {
+                Comparison::Equal => Ok(FilterExpr::Name(vec![value])),
+                Comparison::NotEqual => Ok(FilterExpr::Not(Box::new(FilterExpr::Name(vec![value])))),
+                _ => Err(ParseQueryError(String::from("names can only be compared with = or !="))),
+            }
+        }
+        else {
+            Ok(FilterExpr::Compare(field.parse()?, comparison, parse_number(&value)?))
+        }
+    }
+
+    fn value(&mut self) -> Result<String, ParseQueryError> {
+        match self.next() {
+            Some(Token::Word(word)) | Some(Token::Quoted(word)) => Ok(word),
+            Some(token) => Err(ParseQueryError(format!("expected a value, found {}", token))),
+            None => Err(ParseQueryError(String::from("expected a value"))),
+        }
+    }
+
+    /// `(VALUE, ...)`. The parentheses can be left off.
+    fn list(&mut self) -> Result<Vec<String>, ParseQueryError> {
+        let parenthesized = self.peek() == Some(&Token::Open);
+        if parenthesized {
+            self.pos += 1;
+        }
+
+        let mut values = vec![self.value()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            values.push(self.value()?);
+        }
+
+        if parenthesized && self.next() != Some(Token::Close) {
+            return Err(ParseQueryError(String::from("missing \")\"")));
+        }
+        Ok(values)
+    }
+}
+
+fn parse_number(s: &str) -> Result<f32, ParseQueryError> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).map(|value| value as f32).ok(),
+        None => f32::from_str(s).ok(),
+    };
+    result.ok_or_else(|| ParseQueryError(format!("bad number \"{}\"", s)))
+}
+
+/// Matches `name` against a pattern where `*` is any run of characters and
+/// `?` any one, ignoring case.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_uppercase().chars().collect();
+    let name: Vec<char> = name.to_uppercase().chars().collect();
+
+    // After a `*`, remember where it was and how much of the name it covered
+    // so a failed match can back up and let it cover one more character.
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        }
+        else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        }
+        else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        }
+        else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetObject, Object, Position};
+    use sa2_set::transform::{Field, Comparison};
+
+    use obj_table::ObjectTable;
+    use super::{FilterExpr, glob_matches};
+
+    fn at(id: u16, y: f32) -> SetObject {
+        SetObject {
+            object: Object(id),
+            position: Position { x: 0.0, y, z: 0.0 },
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let expr: FilterExpr = "name=RING* && y > 100".parse().unwrap();
+        assert_eq!(expr, FilterExpr::And(
+            Box::new(FilterExpr::Name(vec![String::from("RING*")])),
+            Box::new(FilterExpr::Compare(Field::YPosition, Comparison::Greater, 100.0)),
+        ));
+
+        let expr: FilterExpr = "id in (0x0A,0x0B) or not (name != \"E KUMI\")".parse().unwrap();
+        assert_eq!(expr, FilterExpr::Or(
+            Box::new(FilterExpr::In(Field::Object, vec![10.0, 11.0])),
+            Box::new(FilterExpr::Not(Box::new(FilterExpr::Not(Box::new(FilterExpr::Name(vec![String::from("E KUMI")])))))),
+        ));
+
+        assert!("y >".parse::<FilterExpr>().is_err());
+        assert!("(y > 1".parse::<FilterExpr>().is_err());
+        assert!("name < RING".parse::<FilterExpr>().is_err());
+        assert!("q = 1".parse::<FilterExpr>().is_err());
+    }
+
+    #[test]
+    fn matches_names_and_fields() {
+        let table = ObjectTable::from_json(r#"{"common": {"0": "RING", "1": "RING_LINEAR", "7": "KASOKU"}, "levels": {}}"#).unwrap();
+        let names = Some((&table, 13));
+        let expr: FilterExpr = "name=ring* && y > 100".parse().unwrap();
+        assert!(expr.matches(&at(1, 150.0), names));
+        assert!(!expr.matches(&at(1, 50.0), names));
+        assert!(!expr.matches(&at(7, 150.0), names));
+
+        let expr: FilterExpr = "!(id in 0,7)".parse().unwrap();
+        assert!(expr.matches(&at(1, 0.0), None));
+        assert!(!expr.matches(&at(7, 0.0), None));
+
+        assert!(glob_matches("R?NG*", "RING_LINEAR"));
+        assert!(glob_matches("*LIN*R", "RING_LINEAR"));
+        assert!(!glob_matches("RING", "RING_LINEAR"));
+    }
+}
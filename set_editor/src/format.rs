@@ -0,0 +1,347 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use csv;
+use toml;
+use serde_json;
+use sa2_set::{SetFile, SetObject, Object, Rotation, Position};
+
+use error::{self, Error};
+use obj_table::ObjectTable;
+
+/// Text formats a set file can be converted to and from.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Toml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| Format::from_str(&ext.to_lowercase()).ok())
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Toml => "toml",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "toml" => Ok(Format::Toml),
+            _ => Err("Unknown format (expected json, csv or toml)."),
+        }
+    }
+}
+
+/// A set object as written in annotated JSON: its index, name and hex ID
+/// sit next to the usual fields. `index` and `name` are ignored when the file
+/// is read back.
+#[derive(Clone,Debug,Serialize)]
+pub struct AnnotatedObject<'a> {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    object: String,
+    rotation: &'a Rotation,
+    position: &'a Position,
+    attr1: f32,
+    attr2: f32,
+    attr3: f32,
+}
+
+pub fn annotate<'a>(set_objs: &'a SetFile, names: Option<(&'a ObjectTable, u16)>) -> Vec<AnnotatedObject<'a>> {
+    set_objs.0.iter()
+        .enumerate()
+        .map(|(index, obj)| {
+            AnnotatedObject {
+                index,
+                name: names.and_then(|(table, level)| table.lookup(level, obj.object.0)).map(|name| name.as_str()),
+                object: format!("0x{:04X}", obj.object.0),
+                rotation: &obj.rotation,
+                position: &obj.position,
+                attr1: obj.attr1,
+                attr2: obj.attr2,
+                attr3: obj.attr3,
+            }
+        })
+        .collect()
+}
+
+/// Reads an object ID written as `0x`-prefixed hex, an object name or alias
+/// from the table, or a decimal number, in that order of preference. Bare
+/// digits are decimal, as JSON numbers and `-t` queries are.
+pub fn parse_object_id(text: &str, names: Option<(&ObjectTable, u16)>) -> Result<u16, String> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return u16::from_str_radix(hex, 16).map_err(|_| format!("Bad object id \"{}\".", text));
+    }
+    if let Some(object) = names.and_then(|(table, level)| table.find(level, text)) {
+        return Ok(object);
+    }
+    u16::from_str(text).map_err(|_| {
+        match names {
+            Some((_, level)) => format!("Unknown object \"{}\" for level {} (expected a name, a decimal id or 0x and a hex id).", text, level),
+            None => format!("Bad object id \"{}\" (expected a decimal id or 0x and a hex id).", text),
+        }
+    })
+}
+
+/// An object ID in a JSON file: a number, or a hex string or name.
+#[derive(Clone,Debug,Deserialize)]
+#[serde(untagged)]
+enum JsonObjectId {
+    Number(u16),
+    Text(String),
+}
+
+/// A set object as read from JSON, before names are resolved.
+#[derive(Clone,Debug,Deserialize)]
+struct JsonObject {
+    object: JsonObjectId,
+    rotation: Rotation,
+    position: Position,
+    attr1: f32,
+    attr2: f32,
+    attr3: f32,
+}
+
+/// Reads a JSON set file. Besides the plain format this accepts annotated
+/// files, whose object IDs are hex strings or object names.
+pub fn read_json<R>(reader: R, names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile>
+    where R: Read
+{
+    let json_objs: Vec<JsonObject> = serde_json::from_reader(reader)?;
+    let mut objs = Vec::with_capacity(json_objs.len());
+    for (idx, obj) in json_objs.into_iter().enumerate() {
+        let object = match obj.object {
+            JsonObjectId::Number(object) => object,
+            JsonObjectId::Text(ref text) => parse_object_id(text, names).map_err(|e| Error::object(idx, e))?,
+        };
+        objs.push(SetObject {
+            object: Object(object),
+            rotation: obj.rotation,
+            position: obj.position,
+            attr1: obj.attr1,
+            attr2: obj.attr2,
+            attr3: obj.attr3,
+        });
+    }
+    Ok(SetFile(objs))
+}
+
+/// One set object flattened into named columns, used for both CSV rows and
+/// TOML tables. Object IDs are written as `0x` hex strings and can be read
+/// back from names or decimal numbers too; `index` and `name` are informational and ignored when
+/// reading.
+#[derive(Clone,Debug,Serialize,Deserialize)]
+struct ObjectRow {
+    #[serde(default)]
+    index: Option<usize>,
+    object: String,
+    #[serde(default)]
+    name: Option<String>,
+    rot_x: u16,
+    rot_y: u16,
+    rot_z: u16,
+    pos_x: f32,
+    pos_y: f32,
+    pos_z: f32,
+    attr1: f32,
+    attr2: f32,
+    attr3: f32,
+}
+
+impl ObjectRow {
+    fn from_obj(index: usize, obj: &SetObject, obj_name: Option<String>) -> ObjectRow {
+        ObjectRow {
+            index: Some(index),
+            object: format!("0x{:04X}", obj.object.0),
+            name: obj_name,
+            rot_x: obj.rotation.x,
+            rot_y: obj.rotation.y,
+            rot_z: obj.rotation.z,
+            pos_x: obj.position.x,
+            pos_y: obj.position.y,
+            pos_z: obj.position.z,
+            attr1: obj.attr1,
+            attr2: obj.attr2,
+            attr3: obj.attr3,
+        }
+    }
+
+    fn to_obj(&self, names: Option<(&ObjectTable, u16)>) -> Result<SetObject, String> {
+        let object = parse_object_id(&self.object, names)?;
+
+        Ok(SetObject {
+            object: Object(object),
+            rotation: Rotation {
+                x: self.rot_x,
+                y: self.rot_y,
+                z: self.rot_z,
+            },
+            position: Position {
+                x: self.pos_x,
+                y: self.pos_y,
+                z: self.pos_z,
+            },
+            attr1: self.attr1,
+            attr2: self.attr2,
+            attr3: self.attr3,
+        })
+    }
+}
+
+#[derive(Serialize,Deserialize)]
+struct TomlSetFile {
+    #[serde(default)]
+    object: Vec<ObjectRow>,
+}
+
+fn to_rows(set_objs: &SetFile, names: Option<(&ObjectTable, u16)>) -> Vec<ObjectRow> {
+    set_objs.0.iter()
+        .enumerate()
+        .map(|(idx, obj)| {
+            let name = names.and_then(|(table, level)| table.lookup(level, obj.object.0)).cloned();
+            ObjectRow::from_obj(idx, obj, name)
+        })
+        .collect()
+}
+
+fn from_rows(rows: &[ObjectRow], names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile> {
+    let mut objs = Vec::with_capacity(rows.len());
+    for (idx, row) in rows.iter().enumerate() {
+        objs.push(row.to_obj(names).map_err(|e| Error::object(idx, e))?);
+    }
+    Ok(SetFile(objs))
+}
+
+pub fn write_csv<W>(writer: W, set_objs: &SetFile, names: Option<(&ObjectTable, u16)>) -> error::Result<()>
+    where W: Write
+{
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for row in to_rows(set_objs, names) {
+        csv_writer.serialize(row)?;
+    }
+    Ok(csv_writer.flush()?)
+}
+
+pub fn read_csv<R>(reader: R, names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile>
+    where R: Read
+{
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let mut rows = Vec::new();
+    for row in csv_reader.deserialize() {
+        rows.push(row?);
+    }
+    from_rows(&rows, names)
+}
+
+pub fn write_toml<W>(mut writer: W, set_objs: &SetFile, names: Option<(&ObjectTable, u16)>) -> error::Result<()>
+    where W: Write
+{
+    let toml_file = TomlSetFile {
+        object: to_rows(set_objs, names),
+    };
+    let text = toml::to_string(&toml_file)?;
+    Ok(writer.write_all(text.as_bytes())?)
+}
+
+pub fn read_toml<R>(mut reader: R, names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile>
+    where R: Read
+{
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let toml_file: TomlSetFile = toml::from_str(&text)?;
+    from_rows(&toml_file.object, names)
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetFile, SetObject, Object, Rotation, Position};
+
+    use serde_json;
+
+    use obj_table::ObjectTable;
+    use super::{annotate, read_json, write_csv, read_csv, write_toml, read_toml};
+
+    fn sample() -> SetFile {
+        let ring = SetObject {
+            object: Object(0x0A),
+            rotation: Rotation { x: 0, y: 0x4000, z: 0 },
+            position: Position { x: 1.5, y: -20.0, z: 300.25 },
+            attr2: 3.0,
+            ..SetObject::default()
+        };
+        SetFile(vec![SetObject::default(), ring])
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut buf = Vec::new();
+        write_csv(&mut buf, &sample(), None).unwrap();
+        assert!(String::from_utf8(buf.clone()).unwrap().contains("0x000A"));
+
+        let read = read_csv(buf.as_slice(), None).unwrap();
+        assert_eq!(read.0.len(), 2);
+        assert_eq!(read.0[1].object.0, 0x0A);
+        assert_eq!(read.0[1].rotation.y, 0x4000);
+        assert_eq!(read.0[1].position.z, 300.25);
+        assert_eq!(read.0[1].attr2, 3.0);
+    }
+
+    #[test]
+    fn annotated_json_reads_back() {
+        let table = ObjectTable::builtin();
+        let set_objs = sample();
+        let json = serde_json::to_string(&annotate(&set_objs, Some((&table, 13)))).unwrap();
+        assert!(json.starts_with(r#"[{"index":0,"name":"RING","object":"0x0000","#));
+
+        let read = read_json(json.as_bytes(), None).unwrap();
+        assert_eq!(read.0, set_objs.0);
+
+        let by_name = json.replace(r#""object":"0x0000""#, r#""object":"ring""#).replace(r#""object":"0x000A""#, r#""object":10"#);
+        let read = read_json(by_name.as_bytes(), Some((&table, 13))).unwrap();
+        assert_eq!(read.0, set_objs.0);
+        assert!(read_json(by_name.as_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut buf = Vec::new();
+        write_toml(&mut buf, &sample(), None).unwrap();
+
+        let read = read_toml(buf.as_slice(), None).unwrap();
+        assert_eq!(read.0.len(), 2);
+        assert_eq!(read.0[1].object.0, 0x0A);
+        assert_eq!(read.0[1].position.y, -20.0);
+    }
+
+    #[test]
+    fn csv_rejects_bad_object_id() {
+        let text = "object,rot_x,rot_y,rot_z,pos_x,pos_y,pos_z,attr1,attr2,attr3\nZZ,0,0,0,0,0,0,0,0,0\n";
+        assert!(read_csv(text.as_bytes(), None).is_err());
+        let text = "object,rot_x,rot_y,rot_z,pos_x,pos_y,pos_z,attr1,attr2,attr3\n0A,0,0,0,0,0,0,0,0,0\n";
+        assert!(read_csv(text.as_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn csv_reads_bare_ids_as_decimal() {
+        let text = "object,rot_x,rot_y,rot_z,pos_x,pos_y,pos_z,attr1,attr2,attr3\n10,0,0,0,0,0,0,0,0,0\n0x10,0,0,0,0,0,0,0,0,0\n";
+        let read = read_csv(text.as_bytes(), None).unwrap();
+        assert_eq!(read.0[0].object.0, 10);
+        assert_eq!(read.0[1].object.0, 0x10);
+    }
+}
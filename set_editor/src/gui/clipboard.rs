@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use gdk::Atom;
+use gtk::prelude::*;
+use gtk::{Builder, Clipboard, Entry};
+use sa2_set::{SetObject, Position};
+
+use error;
+use format;
+use obj_table::ObjectTable;
+
+/// Moves set objects through the system clipboard as the same JSON the
+/// command line writes, so they can be pasted into another editor window or
+/// a text editor and back.
+#[derive(Clone)]
+pub struct ObjectClipboard {
+    clipboard: Clipboard,
+    offset_x: Entry,
+    offset_y: Entry,
+    offset_z: Entry,
+}
+
+impl ObjectClipboard {
+    pub fn new(builder: &Builder) -> ObjectClipboard {
+        ObjectClipboard {
+            clipboard: Clipboard::get(&Atom::intern("CLIPBOARD")),
+            offset_x: builder.get_object("Offset X Entry").unwrap(),
+            offset_y: builder.get_object("Offset Y Entry").unwrap(),
+            offset_z: builder.get_object("Offset Z Entry").unwrap(),
+        }
+    }
+
+    pub fn copy(&self, objs: &[SetObject]) -> error::Result<()> {
+        self.clipboard.set_text(&to_json(objs)?);
+        Ok(())
+    }
+
+    /// Reads objects from the clipboard. Object names are looked up in
+    /// `names` like they are for JSON files.
+    pub fn paste(&self, names: Option<(&ObjectTable, u16)>) -> error::Result<Vec<SetObject>> {
+        let text = self.clipboard.wait_for_text().unwrap_or_default();
+        from_json(&text, names)
+    }
+
+    /// How far pasted and duplicated objects are moved from the originals,
+    /// from the Paste Offset window. Empty fields count as zero.
+    pub fn offset(&self) -> Option<Position> {
+        let parse = |entry: &Entry| {
+            let text = entry.get_text().unwrap_or_default();
+            if text.trim().is_empty() {
+                Some(0.0)
+            }
+            else {
+                f32::from_str(text.trim()).ok()
+            }
+        };
+
+        Some(Position {
+            x: parse(&self.offset_x)?,
+            y: parse(&self.offset_y)?,
+            z: parse(&self.offset_z)?,
+        })
+    }
+}
+
+/// Set objects as the JSON the clipboard and drag and drop carry.
+pub fn to_json(objs: &[SetObject]) -> error::Result<String> {
+    let mut json = Vec::new();
+    ::write_json(&mut json, objs, false)?;
+    Ok(String::from_utf8_lossy(&json).into_owned())
+}
+
+pub fn from_json(text: &str, names: Option<(&ObjectTable, u16)>) -> error::Result<Vec<SetObject>> {
+    format::read_json(text.as_bytes(), names).map(|set_objs| set_objs.0)
+}
@@ -0,0 +1,526 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+
+use gtk::prelude::*;
+use gtk::{self, Builder, Window, Notebook, Paned, Label, Button, IconSize, ReliefStyle, Orientation, TreeView, TreeViewColumn, TreeIter, TreePath, ListStore, ToValue, TreeModelFilter, TreeModelSort, Entry, EntryIconPosition, CellRendererText, DrawingArea, TreeViewGridLines, MessageDialog, MessageType, ButtonsType, ResponseType};
+use sa2_set::{SetFile, SetFileName, SetObject, Position, PlatformKind};
+
+use error;
+use history::{Edit, History};
+use set_model::{ObjectKey, SetModel};
+use map_view::Viewport;
+use obj_table::ObjectTable;
+use filter_expr::FilterExpr;
+use columns::{ColumnField, ColumnLayout, FIELDS, KEY_COLUMN};
+use super::file_kind::FileKind;
+
+const DOCUMENT_SRC: &'static str = include_str!("document.glade");
+
+/// A set file open in its own tab, with everything that goes with it: where
+/// it's saved, which level's names it shows, its undo history and its grid
+/// and map.
+#[derive(Clone)]
+pub struct Document {
+    pub model: Rc<RefCell<SetModel>>,
+    pub history: Rc<RefCell<History>>,
+    pub file_path: Rc<RefCell<Option<PathBuf>>>,
+    pub file_kind: Rc<Cell<FileKind>>,
+    pub level: Rc<Cell<u16>>,
+    /// What the map shows, or `None` to fit it to the objects when it's next
+    /// drawn.
+    pub viewport: Rc<RefCell<Option<Viewport>>>,
+    /// What the filter bar holds, or `None` to show every object.
+    pub filter: Rc<RefCell<Option<FilterExpr>>>,
+    /// The column layout shared by every tab.
+    pub columns: Rc<RefCell<ColumnLayout>>,
+    /// The layout this tab's grid was last arranged in.
+    shown_columns: Rc<RefCell<ColumnLayout>>,
+    /// Set while the grid's columns are moved to match `columns`, so the
+    /// moves aren't taken for the user dragging them.
+    arranging: Rc<Cell<bool>>,
+    obj_table: Rc<RefCell<Option<ObjectTable>>>,
+    pub page: Paned,
+    pub set_list: ListStore,
+    /// `set_list` with the rows the filter hides left out.
+    filtered: TreeModelFilter,
+    /// `filtered` in the order the grid is sorted by, which is what the grid
+    /// shows and its paths refer to.
+    sorted: TreeModelSort,
+    pub set_grid: TreeView,
+    pub map_view: DrawingArea,
+    pub close_button: Button,
+    tab_label: Label,
+    notebook: Notebook,
+    window: Window,
+}
+
+impl Document {
+    /// Makes a tab for `set_objs` and adds it to the end of `notebook`.
+    pub fn new(set_objs: SetFile, level: u16, obj_table: &Rc<RefCell<Option<ObjectTable>>>, columns: &Rc<RefCell<ColumnLayout>>, notebook: &Notebook, window: &Window) -> Document {
+        let builder = Builder::new();
+        builder.add_from_string(DOCUMENT_SRC).unwrap();
+
+        let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
+        set_grid.set_headers_clickable(true);
+        set_grid.set_property_enable_grid_lines(TreeViewGridLines::Both);
+
+        let tab_label = Label::new(None);
+        let close_button = Button::new_from_icon_name("window-close", IconSize::Menu.into());
+        close_button.set_relief(ReliefStyle::None);
+        close_button.set_tooltip_text(Some("Close this file"));
+        let tab = gtk::Box::new(Orientation::Horizontal, 4);
+        tab.pack_start(&tab_label, true, true, 0);
+        tab.pack_start(&close_button, false, false, 0);
+        tab.show_all();
+
+        let doc = Document {
+            model: Rc::new(RefCell::new(SetModel::new(set_objs))),
+            history: Rc::new(RefCell::new(History::new())),
+            file_path: Rc::new(RefCell::new(None)),
+            file_kind: Rc::new(Cell::new(FileKind::SetFile(PlatformKind::Pc))),
+            level: Rc::new(Cell::new(level)),
+            viewport: Rc::new(RefCell::new(None)),
+            filter: Rc::new(RefCell::new(None)),
+            columns: columns.clone(),
+            shown_columns: Rc::new(RefCell::new(ColumnLayout::default())),
+            arranging: Rc::new(Cell::new(false)),
+            obj_table: obj_table.clone(),
+            page: builder.get_object("Document").unwrap(),
+            set_list: builder.get_object("Set Objects").unwrap(),
+            filtered: builder.get_object("Filtered Objects").unwrap(),
+            sorted: builder.get_object("Sorted Objects").unwrap(),
+            set_grid,
+            map_view: builder.get_object("Map View").unwrap(),
+            close_button,
+            tab_label,
+            notebook: notebook.clone(),
+            window: window.clone(),
+        };
+
+        for &field in FIELDS.iter() {
+            doc.add_column(field);
+        }
+        doc.apply_columns();
+        doc.connect_columns();
+        doc.connect_map();
+        doc.connect_filter(&builder);
+
+        notebook.append_page(&doc.page, Some(&tab));
+        notebook.set_tab_reorderable(&doc.page, true);
+        doc.update_grid();
+        doc.update_title();
+        doc
+    }
+
+    /// The file name shown in the tab and the window title.
+    pub fn name(&self) -> String {
+        self.file_path.borrow().as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("Untitled"))
+    }
+
+    pub fn window_title(&self) -> String {
+        let dirty = if self.history.borrow().is_dirty() { "*" } else { "" };
+        format!("{}{} - Set Editor", dirty, self.name())
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.notebook.page_num(&self.page) == self.notebook.get_current_page()
+    }
+
+    /// Whether the tab is a new, empty file nobody has touched, which Open
+    /// can reuse instead of adding another tab.
+    pub fn is_pristine(&self) -> bool {
+        self.file_path.borrow().is_none() && !self.history.borrow().is_dirty() && self.model.borrow().set_file().0.is_empty()
+    }
+
+    pub fn update_title(&self) {
+        let dirty = if self.history.borrow().is_dirty() { "*" } else { "" };
+        self.tab_label.set_text(&format!("{}{}", dirty, self.name()));
+        if self.is_current() {
+            self.window.set_title(&self.window_title());
+        }
+    }
+
+    pub fn load(&self, filename: &Path, kind: FileKind) -> error::Result<Option<String>> {
+        let set_name = SetFileName::from_path(filename);
+        let level = set_name.as_ref().map(|name| name.level).unwrap_or(self.level.get());
+        let set_objs = {
+            let obj_table = self.obj_table.borrow();
+            kind.read(filename, obj_table.as_ref().map(|table| (table, level)))?
+        };
+
+        let warning = set_name.as_ref()
+            .map(|name| name.object_limit())
+            .filter(|limit| set_objs.0.len() > *limit)
+            .map(|limit| format!("{} objects exceeds the limit of {}.", set_objs.0.len(), limit));
+
+        *self.model.borrow_mut() = SetModel::new(set_objs);
+        *self.history.borrow_mut() = History::new();
+        *self.file_path.borrow_mut() = Some(filename.to_path_buf());
+        self.file_kind.set(kind);
+        self.level.set(level);
+        *self.viewport.borrow_mut() = None;
+
+        self.update_grid();
+        self.update_title();
+        self.map_view.queue_draw();
+        Ok(warning)
+    }
+
+    /// Writes the set file to `filename` as `kind`, which becomes where a
+    /// plain Save goes from then on.
+    pub fn save(&self, filename: &Path, kind: FileKind) -> error::Result<()> {
+        {
+            let obj_table = self.obj_table.borrow();
+            let names = obj_table.as_ref().map(|table| (table, self.level.get()));
+            kind.write(filename, self.model.borrow().set_file(), names)?;
+        }
+
+        self.history.borrow_mut().mark_saved();
+        *self.file_path.borrow_mut() = Some(filename.to_path_buf());
+        self.file_kind.set(kind);
+        self.update_title();
+        Ok(())
+    }
+
+    /// Shows object names for `level` instead.
+    pub fn set_level(&self, level: u16) {
+        self.level.set(level);
+        self.update_grid();
+    }
+
+    /// Applies `edit` to the set file and records it so it can be undone.
+    pub fn perform(&self, edit: Edit) {
+        self.history.borrow_mut().perform(&mut self.model.borrow_mut(), edit);
+        self.update_title();
+    }
+
+    /// Asks whether unsaved changes can be thrown away. Returns true straight
+    /// away if there aren't any.
+    pub fn confirm_discard(&self) -> bool {
+        if !self.history.borrow().is_dirty() {
+            return true;
+        }
+
+        let message = format!("{} has unsaved changes. Discard them?", self.name());
+        let dialog = MessageDialog::new(Some(&self.window), gtk::DIALOG_MODAL, MessageType::Question, ButtonsType::YesNo, &message);
+        let response = dialog.run();
+        dialog.destroy();
+        response == Into::<i32>::into(ResponseType::Yes)
+    }
+
+    pub fn update_grid(&self) {
+        self.set_list.clear();
+
+        let mut index = 0;
+        for (key, obj) in self.model.borrow().iter() {
+            let iter = self.set_list.append();
+            self.set_row(&iter, index, key, obj);
+            index += 1;
+        }
+    }
+
+    /// Shows the object behind `key` again after it was changed, without
+    /// rebuilding the grid and losing the selection.
+    pub fn update_row(&self, key: ObjectKey) {
+        let model = self.model.borrow();
+        if let (Some(iter), Some(index), Some(obj)) = (self.find_row(key), model.index_of(key), model.get(key)) {
+            self.set_row(&iter, index as u32, key, obj);
+        }
+    }
+
+    fn set_row(&self, iter: &TreeIter, index: u32, key: ObjectKey, obj: &SetObject) {
+        let texts: Vec<String> = {
+            let layout = self.shown_columns.borrow();
+            self.with_names(|names| FIELDS.iter().map(|&field| layout.column(field).text(index, obj, names)).collect())
+        };
+        let values: Vec<f64> = FIELDS.iter().map(|&field| field.value(index, obj)).collect();
+
+        let store_columns: Vec<u32> = (0..KEY_COLUMN as u32 + 1).collect();
+        let mut row: Vec<&dyn ToValue> = texts.iter().map(|text| text as &dyn ToValue)
+            .chain(values.iter().map(|value| value as &dyn ToValue))
+            .collect();
+        row.push(&key.0);
+        self.set_list.set(iter, &store_columns, &row);
+    }
+
+    /// Writes every row again, as after a column's format changed.
+    fn refresh_rows(&self) {
+        let model = self.model.borrow();
+        let mut iter = self.set_list.get_iter_first();
+        while let Some(row) = iter {
+            let key = self.row_key(&row);
+            if let (Some(index), Some(obj)) = (model.index_of(key), model.get(key)) {
+                self.set_row(&row, index as u32, key, obj);
+            }
+
+            if self.set_list.iter_next(&row) {
+                iter = Some(row);
+            }
+            else {
+                iter = None;
+            }
+        }
+    }
+
+    pub fn row_key(&self, iter: &TreeIter) -> ObjectKey {
+        ObjectKey(self.set_list.get_value(iter, KEY_COLUMN).get::<u32>().unwrap())
+    }
+
+    /// The key of a row as the grid shows it, sorted and filtered.
+    fn shown_key(&self, iter: &TreeIter) -> ObjectKey {
+        ObjectKey(self.sorted.get_value(iter, KEY_COLUMN).get::<u32>().unwrap())
+    }
+
+
+    fn find_row(&self, key: ObjectKey) -> Option<TreeIter> {
+        let iter = self.set_list.get_iter_first()?;
+        loop {
+            if self.row_key(&iter) == key {
+                return Some(iter);
+            }
+            if !self.set_list.iter_next(&iter) {
+                return None;
+            }
+        }
+    }
+
+    /// The keys of the selected rows, in the order they're shown.
+    pub fn selected_keys(&self) -> Vec<ObjectKey> {
+        let (paths, _) = self.set_grid.get_selection().get_selected_rows();
+        paths.iter()
+            .filter_map(|path| self.sorted.get_iter(path))
+            .map(|iter| self.shown_key(&iter))
+            .collect()
+    }
+
+    /// Selects the rows showing `keys` and scrolls to the first of them.
+    /// Objects the filter hides stay unselected.
+    pub fn select_keys(&self, keys: &[ObjectKey]) {
+        let selection = self.set_grid.get_selection();
+        selection.unselect_all();
+
+        let mut first_path = None;
+        let mut iter = self.sorted.get_iter_first();
+        while let Some(row) = iter {
+            let key = self.shown_key(&row);
+            if keys.contains(&key) {
+                selection.select_iter(&row);
+                if keys.first() == Some(&key) {
+                    first_path = self.sorted.get_path(&row);
+                }
+            }
+
+            if self.sorted.iter_next(&row) {
+                iter = Some(row);
+            }
+            else {
+                iter = None;
+            }
+        }
+
+        if let Some(path) = first_path {
+            self.set_grid.scroll_to_cell(Some(&path), None, false, 0.0, 0.0);
+        }
+    }
+
+    /// Inserts copies of `objs` moved by `offset` after the last selected
+    /// object, then selects them.
+    pub fn paste_objects(&self, objs: Vec<SetObject>, offset: &Position) {
+        let objs = objs.into_iter().map(|mut obj| {
+            obj.position.x += offset.x;
+            obj.position.y += offset.y;
+            obj.position.z += offset.z;
+            obj
+        }).collect();
+
+        let after = self.model.borrow().last_of(&self.selected_keys());
+        let (keys, edit) = self.model.borrow_mut().insert_after(after, objs);
+        self.perform(edit);
+
+        self.update_grid();
+        self.select_keys(&keys);
+    }
+
+    /// The object names for this tab's level, for reading and writing text.
+    pub fn with_names<F, R>(&self, f: F) -> R
+        where F: FnOnce(Option<(&ObjectTable, u16)>) -> R
+    {
+        let obj_table = self.obj_table.borrow();
+        f(obj_table.as_ref().map(|table| (table, self.level.get())))
+    }
+
+    /// Hides the rows the filter bar's expression doesn't match, and lets
+    /// every shown row be selected at once.
+    fn connect_filter(&self, builder: &Builder) {
+        {
+            let model = self.model.clone();
+            let filter = self.filter.clone();
+            let self_clone = self.clone();
+            self.filtered.set_visible_func(move |list, iter| {
+                let filter = filter.borrow();
+                let filter = match *filter {
+                    Some(ref filter) => filter,
+                    None => return true,
+                };
+                // Rows are shown while the model is being changed; they're
+                // filtered again once the grid catches up.
+                let model = match model.try_borrow() {
+                    Ok(model) => model,
+                    Err(_) => return true,
+                };
+                let key = ObjectKey(list.get_value(iter, KEY_COLUMN).get::<u32>().unwrap_or(0));
+                match model.get(key) {
+                    Some(obj) => self_clone.with_names(|names| filter.matches(obj, names)),
+                    None => true,
+                }
+            });
+        }
+
+        {
+            let filter_entry: Entry = builder.get_object("Filter Entry").unwrap();
+            let self_clone = self.clone();
+            filter_entry.connect_changed(move |entry| {
+                let text = entry.get_text().unwrap_or_default();
+                let parsed = if text.trim().is_empty() {
+                    Ok(None)
+                }
+                else {
+                    text.parse::<FilterExpr>().map(Some)
+                };
+
+                match parsed {
+                    Ok(filter) => {
+                        entry.set_icon_from_icon_name(EntryIconPosition::Primary, Some("edit-find-symbolic"));
+                        entry.set_icon_tooltip_text(EntryIconPosition::Primary, None);
+                        *self_clone.filter.borrow_mut() = filter;
+                        self_clone.filtered.refilter();
+                    }
+                    Err(err) => {
+                        // Keep showing what the last good expression matched
+                        // while the new one is still being typed.
+                        entry.set_icon_from_icon_name(EntryIconPosition::Primary, Some("dialog-warning"));
+                        entry.set_icon_tooltip_text(EntryIconPosition::Primary, Some(err.to_string().as_str()));
+                    }
+                }
+            });
+        }
+
+        {
+            let select_button: Button = builder.get_object("Select Shown").unwrap();
+            let set_grid = self.set_grid.clone();
+            select_button.connect_clicked(move |_| {
+                set_grid.get_selection().select_all();
+            });
+        }
+    }
+
+    /// Adds the grid column showing `field`, editable if the field is.
+    fn add_column(&self, field: ColumnField) {
+        let renderer = CellRendererText::new();
+        renderer.set_property_editable(field.editable());
+        let column = TreeViewColumn::new();
+        column.set_title(field.title());
+        column.set_resizable(true);
+        column.set_reorderable(true);
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", field.text_column());
+        column.set_sort_column_id(field.sort_column());
+        let width = self.columns.borrow().column(field).width;
+        if width > 0 {
+            column.set_fixed_width(width);
+        }
+
+        if field.editable() {
+            let self_clone = self.clone();
+            renderer.connect_edited(move |_, tree_path, text| {
+                self_clone.edit_cell(field, &tree_path, text);
+            });
+        }
+
+        self.set_grid.append_column(&column);
+    }
+
+    /// Sets `field` of the object shown at `path` from what was typed into
+    /// its cell. Text the column's format can't read is ignored.
+    fn edit_cell(&self, field: ColumnField, path: &TreePath, text: &str) {
+        let key = match self.sorted.get_iter(path) {
+            Some(iter) => self.shown_key(&iter),
+            None => return,
+        };
+        let column = self.shown_columns.borrow().column(field);
+        let edit = {
+            let model = self.model.borrow();
+            model.get(key).and_then(|obj| {
+                let mut new = *obj;
+                column.edit(&mut new, text).ok()?;
+                model.replace(key, new)
+            })
+        };
+
+        if let Some(edit) = edit {
+            self.perform(edit);
+            self.update_row(key);
+        }
+    }
+
+    /// Arranges the grid's columns as the shared layout has them, rewriting
+    /// the rows if a format changed since they were written.
+    pub fn apply_columns(&self) {
+        let layout = self.columns.borrow().clone();
+        let refresh = {
+            let shown = self.shown_columns.borrow();
+            layout.0.iter().any(|column| shown.column(column.field).format != column.format)
+        };
+        *self.shown_columns.borrow_mut() = layout.clone();
+
+        self.arranging.set(true);
+        let grid_columns = self.set_grid.get_columns();
+        let mut previous: Option<TreeViewColumn> = None;
+        for column in layout.0.iter() {
+            if let Some(grid_column) = grid_columns.iter().find(|grid_column| grid_column.get_sort_column_id() == column.field.sort_column()) {
+                grid_column.set_visible(column.visible);
+                self.set_grid.move_column_after(grid_column, previous.as_ref());
+                previous = Some(grid_column.clone());
+            }
+        }
+        self.arranging.set(false);
+
+        if refresh {
+            self.refresh_rows();
+        }
+    }
+
+    /// Copies the widths the grid's columns were resized to into the shared
+    /// layout, so they can be saved. Hidden columns keep the width they had.
+    pub fn store_widths(&self) {
+        let mut layout = self.columns.borrow_mut();
+        for grid_column in self.set_grid.get_columns() {
+            let field = ColumnField::from_sort_column(grid_column.get_sort_column_id());
+            if let Some(column) = field.and_then(|field| layout.column_mut(field)) {
+                if grid_column.get_visible() {
+                    column.width = grid_column.get_width();
+                }
+            }
+        }
+    }
+
+    /// Keeps the shared layout in the order the user drags the headers into.
+    fn connect_columns(&self) {
+        let self_clone = self.clone();
+        self.set_grid.connect_columns_changed(move |grid| {
+            if self_clone.arranging.get() {
+                return;
+            }
+
+            let order: Vec<ColumnField> = grid.get_columns().iter()
+                .filter_map(|column| ColumnField::from_sort_column(column.get_sort_column_id()))
+                .collect();
+            self_clone.columns.borrow_mut().reorder(&order);
+            self_clone.shown_columns.borrow_mut().reorder(&order);
+        });
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::path::Path;
+
+use sa2_set::{SetFile, PlatformKind, Dreamcast, GameCube, Pc};
+
+use error;
+use format::Format;
+use obj_table::ObjectTable;
+
+/// How a file open in the editor is stored, so Save can write it back the
+/// same way it was read.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum FileKind {
+    SetFile(PlatformKind),
+    Text(Format),
+}
+
+impl FileKind {
+    /// Every kind, in the order the file choosers list them.
+    pub const ALL: [FileKind; 6] = [
+        FileKind::SetFile(PlatformKind::Pc),
+        FileKind::SetFile(PlatformKind::GameCube),
+        FileKind::SetFile(PlatformKind::Dreamcast),
+        FileKind::Text(Format::Json),
+        FileKind::Text(Format::Csv),
+        FileKind::Text(Format::Toml),
+    ];
+
+    /// Guesses the kind from the extension. Set binaries don't say which
+    /// platform they're for, so `.bin` files are taken to be `platform`.
+    pub fn from_path(path: &Path, platform: PlatformKind) -> Option<FileKind> {
+        if path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("bin")).unwrap_or(false) {
+            Some(FileKind::SetFile(platform))
+        }
+        else {
+            Format::from_path(path).map(FileKind::Text)
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            FileKind::SetFile(_) => "bin",
+            FileKind::Text(format) => format.extension(),
+        }
+    }
+
+    /// The glob a file chooser filter for this kind matches.
+    pub fn pattern(&self) -> String {
+        format!("*.{}", self.extension())
+    }
+
+    pub fn read(&self, path: &Path, names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile> {
+        match *self {
+            FileKind::SetFile(PlatformKind::Pc) => ::read_set_file::<Pc>(path),
+            FileKind::SetFile(PlatformKind::GameCube) => ::read_set_file::<GameCube>(path),
+            FileKind::SetFile(PlatformKind::Dreamcast) => ::read_set_file::<Dreamcast>(path),
+            FileKind::Text(format) => ::read_text_file(path, format, names),
+        }
+    }
+
+    pub fn write(&self, path: &Path, set_objs: &SetFile, names: Option<(&ObjectTable, u16)>) -> error::Result<()> {
+        match *self {
+            FileKind::SetFile(PlatformKind::Pc) => ::write_set_file::<Pc>(path, set_objs),
+            FileKind::SetFile(PlatformKind::GameCube) => ::write_set_file::<GameCube>(path, set_objs),
+            FileKind::SetFile(PlatformKind::Dreamcast) => ::write_set_file::<Dreamcast>(path, set_objs),
+            FileKind::Text(format) => ::write_text_file(path, set_objs, format, false, false, names),
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileKind::SetFile(PlatformKind::Pc) => write!(f, "PC set file"),
+            FileKind::SetFile(PlatformKind::GameCube) => write!(f, "GameCube set file"),
+            FileKind::SetFile(PlatformKind::Dreamcast) => write!(f, "Dreamcast set file"),
+            FileKind::Text(Format::Json) => write!(f, "JSON file"),
+            FileKind::Text(Format::Csv) => write!(f, "CSV file"),
+            FileKind::Text(Format::Toml) => write!(f, "TOML file"),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+
+use gtk::prelude::*;
+use gtk::{self, Builder, Label, Grid, SpinButton, CheckButton, ComboBoxText, Orientation, Align};
+
+use obj_attrs::{self, AttrInfo, AttrKind, AttrTable};
+use set_model::ObjectKey;
+use super::document::Document;
+
+/// Shows the selected object's name and attributes with editors that suit
+/// what each attribute means.
+#[derive(Clone)]
+pub struct Inspector {
+    pub attr_table: Rc<RefCell<AttrTable>>,
+    /// The object being shown, so changes to other rows don't rebuild it.
+    shown: Rc<Cell<Option<ObjectKey>>>,
+    /// Set while an editor writes back, so the row it changes doesn't tear
+    /// the editor down under itself.
+    writing: Rc<Cell<bool>>,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector {
+            attr_table: Rc::new(RefCell::new(AttrTable::default())),
+            shown: Rc::new(Cell::new(None)),
+            writing: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Keeps the inspector following `doc`'s selection and edits.
+    pub fn connect(&self, builder: &Builder, doc: &Document) {
+        {
+            let builder = builder.clone();
+            let doc_clone = doc.clone();
+            let self_clone = self.clone();
+            doc.set_grid.get_selection().connect_changed(move |_| {
+                self_clone.show(&builder, &doc_clone);
+            });
+        }
+
+        {
+            let builder = builder.clone();
+            let doc_clone = doc.clone();
+            let self_clone = self.clone();
+            doc.set_list.connect_row_changed(move |_, _, iter| {
+                if self_clone.shown.get() == Some(doc_clone.row_key(iter)) {
+                    self_clone.show(&builder, &doc_clone);
+                }
+            });
+        }
+    }
+
+    /// Rebuilds the panel for `doc`'s selection. Only a single selected object
+    /// can be inspected.
+    pub fn show(&self, builder: &Builder, doc: &Document) {
+        if self.writing.get() {
+            return;
+        }
+
+        let name_label: Label = builder.get_object("Inspector Name").unwrap();
+        let grid: Grid = builder.get_object("Inspector Grid").unwrap();
+        for child in grid.get_children() {
+            grid.remove(&child);
+        }
+        self.shown.set(None);
+
+        let keys = doc.selected_keys();
+        let key = match keys.len() {
+            0 => {
+                name_label.set_text("No object selected");
+                return;
+            }
+            1 => keys[0],
+            count => {
+                name_label.set_text(&format!("{} objects selected", count));
+                return;
+            }
+        };
+        let obj = match doc.model.borrow().get(key) {
+            Some(obj) => *obj,
+            None => return,
+        };
+        self.shown.set(Some(key));
+
+        let info = doc.with_names(|names| names.and_then(|(table, level)| table.info(level, obj.object.0)).cloned());
+        match info {
+            Some(ref info) => name_label.set_text(&format!("{} ({:04X})", info.name, obj.object.0)),
+            None => name_label.set_text(&format!("Object {:04X}", obj.object.0)),
+        }
+
+        let described = info.as_ref()
+            .and_then(|info| self.attr_table.borrow().attributes(info).map(|attrs| attrs.to_vec()))
+            .unwrap_or_default();
+        for index in 0..3 {
+            let attr = described.get(index).cloned()
+                .unwrap_or_else(|| AttrInfo::plain(index, info.as_ref().and_then(|info| info.attributes.get(index))));
+            self.add_attribute(&grid, doc, key, index, &attr, obj_attrs::attribute(&obj, index));
+        }
+        grid.show_all();
+    }
+
+    /// Adds a row for attribute `index` to `grid`: its name, an editor and
+    /// its unit.
+    fn add_attribute(&self, grid: &Grid, doc: &Document, key: ObjectKey, index: usize, attr: &AttrInfo, value: f32) {
+        let row = index as i32;
+        let name = Label::new(Some(attr.name.as_str()));
+        name.set_halign(Align::Start);
+        name.set_valign(Align::Start);
+        if let Some(ref description) = attr.description {
+            name.set_tooltip_text(Some(description.as_str()));
+        }
+        grid.attach(&name, 0, row, 1, 1);
+
+        let editor: gtk::Widget = match attr.kind() {
+            AttrKind::Number => {
+                let spin = SpinButton::new_with_range(attr.min.unwrap_or(-1e9), attr.max.unwrap_or(1e9), attr.step.unwrap_or(1.0));
+                spin.set_digits(3);
+                spin.set_value(value as f64);
+                let self_clone = self.clone();
+                let doc = doc.clone();
+                spin.connect_value_changed(move |spin| {
+                    self_clone.write(&doc, key, index, |_| spin.get_value() as f32);
+                });
+                spin.upcast()
+            }
+            AttrKind::Enum(values) => {
+                let combo = ComboBoxText::new();
+                for (value, name) in values.iter() {
+                    combo.append(Some(value.to_string().as_str()), &format!("{} ({})", name, value));
+                }
+                // Values the data file doesn't know still show up, so picking
+                // from the list is never the only way to see what's there.
+                let current = value as i32;
+                if !values.contains_key(&current) {
+                    combo.append(Some(current.to_string().as_str()), &format!("Unknown ({})", current));
+                }
+                combo.set_active_id(Some(current.to_string().as_str()));
+                let self_clone = self.clone();
+                let doc = doc.clone();
+                combo.connect_changed(move |combo| {
+                    if let Some(picked) = combo.get_active_id().and_then(|id| id.parse::<i32>().ok()) {
+                        self_clone.write(&doc, key, index, |_| picked as f32);
+                    }
+                });
+                combo.upcast()
+            }
+            AttrKind::Flags(flags) => {
+                let checks = gtk::Box::new(Orientation::Vertical, 0);
+                for (&bit, name) in flags.iter() {
+                    let check = CheckButton::new_with_label(name);
+                    check.set_active(obj_attrs::has_flag(value, bit));
+                    let self_clone = self.clone();
+                    let doc = doc.clone();
+                    check.connect_toggled(move |check| {
+                        let on = check.get_active();
+                        self_clone.write(&doc, key, index, |old| obj_attrs::with_flag(old, bit, on));
+                    });
+                    checks.pack_start(&check, false, false, 0);
+                }
+                checks.upcast()
+            }
+        };
+        editor.set_hexpand(true);
+        grid.attach(&editor, 1, row, 1, 1);
+
+        if let Some(ref unit) = attr.unit {
+            let unit = Label::new(Some(unit.as_str()));
+            unit.set_halign(Align::Start);
+            unit.set_valign(Align::Start);
+            grid.attach(&unit, 2, row, 1, 1);
+        }
+    }
+
+    /// Sets attribute `index` of `key` to what `update` makes of its old
+    /// value, as an edit that can be undone.
+    fn write<F>(&self, doc: &Document, key: ObjectKey, index: usize, update: F)
+        where F: FnOnce(f32) -> f32
+    {
+        let edit = {
+            let model = doc.model.borrow();
+            model.get(key).and_then(|obj| {
+                let mut new = *obj;
+                obj_attrs::set_attribute(&mut new, index, update(obj_attrs::attribute(obj, index)));
+                model.replace(key, new)
+            })
+        };
+
+        if let Some(edit) = edit {
+            self.writing.set(true);
+            doc.perform(edit);
+            doc.update_row(key);
+            self.writing.set(false);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use cairo::Context;
+use gdk::ScrollDirection;
+use gtk::prelude::*;
+use sa2_set::SetObject;
+
+use map_view::{self, Viewport};
+use obj_table::ObjectTable;
+use set_model::ObjectKey;
+use svg;
+use super::document::Document;
+
+const MARKER_RADIUS: f64 = 4.0;
+const ARROW_LENGTH: f64 = 12.0;
+const ZOOM_STEP: f64 = 1.25;
+
+/// What a drag that started on the map is doing.
+#[derive(Clone,Copy,Debug)]
+enum Drag {
+    Pan { last_x: f64, last_y: f64 },
+    /// Moving an object. `x` and `z` are where it'll be dropped, and the
+    /// offsets keep it from jumping to the cursor when it's grabbed off
+    /// center.
+    Move { key: ObjectKey, offset_x: f64, offset_z: f64, x: f64, z: f64 },
+}
+
+fn draw_object(cr: &Context, viewport: &Viewport, obj: &SetObject, x: f64, z: f64, names: Option<(&ObjectTable, u16)>, selected: bool) {
+    let (screen_x, screen_y) = viewport.screen_position(x, z);
+    let (r, g, b) = map_view::hsl_to_rgb(svg::object_hue(obj, names) as f64, 0.7, 0.55);
+    cr.set_source_rgb(r, g, b);
+    cr.arc(screen_x, screen_y, MARKER_RADIUS, 0.0, 2.0 * PI);
+    cr.fill();
+
+    // The line shows the Y rotation, with zero facing +X like the SVG plots.
+    let theta = obj.rotation.y as f64 * 2.0 * PI / 65536.0;
+    cr.move_to(screen_x, screen_y);
+    cr.line_to(screen_x + theta.cos() * ARROW_LENGTH, screen_y - theta.sin() * ARROW_LENGTH);
+    cr.stroke();
+
+    if selected {
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.arc(screen_x, screen_y, MARKER_RADIUS + 2.0, 0.0, 2.0 * PI);
+        cr.stroke();
+    }
+}
+
+impl Document {
+    pub(super) fn connect_map(&self) {
+        let map_view = self.map_view.clone();
+        let drag: Rc<RefCell<Option<Drag>>> = Rc::new(RefCell::new(None));
+
+        {
+            let drag = drag.clone();
+            let self_clone = self.clone();
+            map_view.connect_draw(move |map_view, cr| {
+                let width = map_view.get_allocated_width() as f64;
+                let height = map_view.get_allocated_height() as f64;
+                let model = self_clone.model.borrow();
+
+                let mut viewport_opt = self_clone.viewport.borrow_mut();
+                let viewport = viewport_opt.get_or_insert_with(|| Viewport::fit(model.iter().map(|(_, obj)| &obj.position), width, height));
+                // Resizing keeps the same spot in the middle.
+                viewport.width = width;
+                viewport.height = height;
+
+                cr.set_source_rgb(0.15, 0.15, 0.15);
+                cr.paint();
+
+                let selected = self_clone.selected_keys();
+                let drag = *drag.borrow();
+                self_clone.with_names(|names| {
+                    for (key, obj) in model.iter() {
+                        let (x, z) = match drag {
+                            Some(Drag::Move { key: dragged, x, z, .. }) if dragged == key => (x, z),
+                            _ => (obj.position.x as f64, obj.position.z as f64),
+                        };
+                        draw_object(cr, viewport, obj, x, z, names, selected.contains(&key));
+                    }
+                });
+
+                Inhibit(false)
+            });
+        }
+
+        {
+            let drag = drag.clone();
+            let self_clone = self.clone();
+            map_view.connect_button_press_event(move |map_view, event| {
+                if event.get_button() != 1 {
+                    return Inhibit(false);
+                }
+                let viewport = match *self_clone.viewport.borrow() {
+                    Some(viewport) => viewport,
+                    None => return Inhibit(false),
+                };
+                map_view.grab_focus();
+
+                let (screen_x, screen_y) = event.get_position();
+                let model = self_clone.model.borrow();
+                let started = match viewport.object_at(&model, screen_x, screen_y) {
+                    Some(key) => {
+                        self_clone.select_keys(&[key]);
+                        let obj = model.get(key).unwrap();
+                        let (x, z) = viewport.world_position(screen_x, screen_y);
+                        Drag::Move {
+                            key,
+                            offset_x: obj.position.x as f64 - x,
+                            offset_z: obj.position.z as f64 - z,
+                            x: obj.position.x as f64,
+                            z: obj.position.z as f64,
+                        }
+                    }
+                    None => Drag::Pan { last_x: screen_x, last_y: screen_y },
+                };
+                *drag.borrow_mut() = Some(started);
+
+                Inhibit(true)
+            });
+        }
+
+        {
+            let drag = drag.clone();
+            let self_clone = self.clone();
+            map_view.connect_motion_notify_event(move |map_view, event| {
+                let (screen_x, screen_y) = event.get_position();
+                match *drag.borrow_mut() {
+                    Some(Drag::Pan { ref mut last_x, ref mut last_y }) => {
+                        if let Some(ref mut viewport) = *self_clone.viewport.borrow_mut() {
+                            viewport.pan(screen_x - *last_x, screen_y - *last_y);
+                        }
+                        *last_x = screen_x;
+                        *last_y = screen_y;
+                    }
+                    Some(Drag::Move { offset_x, offset_z, ref mut x, ref mut z, .. }) => {
+                        if let Some(viewport) = *self_clone.viewport.borrow() {
+                            let (world_x, world_z) = viewport.world_position(screen_x, screen_y);
+                            *x = world_x + offset_x;
+                            *z = world_z + offset_z;
+                        }
+                    }
+                    None => return Inhibit(false),
+                }
+
+                map_view.queue_draw();
+                Inhibit(true)
+            });
+        }
+
+        {
+            let drag = drag.clone();
+            let self_clone = self.clone();
+            map_view.connect_button_release_event(move |map_view, event| {
+                if event.get_button() != 1 {
+                    return Inhibit(false);
+                }
+
+                // Only X and Z change; the drop is a single edit to undo.
+                let finished = drag.borrow_mut().take();
+                if let Some(Drag::Move { key, x, z, .. }) = finished {
+                    let edit = {
+                        let model = self_clone.model.borrow();
+                        model.get(key).and_then(|obj| {
+                            let mut new = *obj;
+                            new.position.x = x as f32;
+                            new.position.z = z as f32;
+                            model.replace(key, new)
+                        })
+                    };
+                    if let Some(edit) = edit {
+                        self_clone.perform(edit);
+                        self_clone.update_grid();
+                        self_clone.select_keys(&[key]);
+                    }
+                }
+
+                map_view.queue_draw();
+                Inhibit(true)
+            });
+        }
+
+        {
+            let self_clone = self.clone();
+            map_view.connect_scroll_event(move |map_view, event| {
+                let factor = match event.get_direction() {
+                    ScrollDirection::Up => ZOOM_STEP,
+                    ScrollDirection::Down => 1.0 / ZOOM_STEP,
+                    _ => return Inhibit(false),
+                };
+                let (screen_x, screen_y) = event.get_position();
+                if let Some(ref mut viewport) = *self_clone.viewport.borrow_mut() {
+                    viewport.zoom_at(screen_x, screen_y, factor);
+                }
+
+                map_view.queue_draw();
+                Inhibit(true)
+            });
+        }
+
+        // The map draws from the set file, so redraw whenever the grid shows
+        // a change or the selection moves.
+        {
+            let map_view = map_view.clone();
+            self.set_grid.get_selection().connect_changed(move |_| {
+                map_view.queue_draw();
+            });
+        }
+
+        {
+            let map_view = map_view.clone();
+            self.set_list.connect_row_changed(move |_, _, _| {
+                map_view.queue_draw();
+            });
+        }
+
+        {
+            self.set_list.connect_row_deleted(move |_, _| {
+                map_view.queue_draw();
+            });
+        }
+    }
+}
@@ -1,31 +1,73 @@
-mod column_type;
+mod file_kind;
+mod clipboard;
+mod document;
+mod inspector;
+mod map;
 
-use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::cell::{Cell, RefCell};
 use std::str::FromStr;
 
+use gdk::{self, Atom};
 use gtk::prelude::*;
-use gtk::{self, Builder, Window, Statusbar, Adjustment, TreeView, TreeViewColumn, TreeIter, ListStore, CellRendererText, MenuItem, FileChooserDialog, FileChooserAction, ResponseType, TreeViewGridLines, RadioButton, Entry, Button};
-use sa2_set::{SetFile, SetObject, Object, Platform, Dreamcast, GameCube, Pc};
+use gtk::{self, Builder, Window, Widget, Statusbar, Adjustment, Notebook, TreeView, TargetEntry, MenuItem, FileChooserDialog, FileChooserAction, FileFilter, ResponseType, Entry, Button, CheckButton, ComboBoxText, Menu, CheckMenuItem, RadioMenuItem, SeparatorMenuItem, MessageDialog, MessageType, ButtonsType};
+use sa2_set::{SetFile, SetObject, Position, SpatialIndex, Query, PlatformKind};
 
+use error::{self, Error};
+use history::Edit;
+use set_model::ObjectKey;
 use obj_table::ObjectTable;
-use self::column_type::{ColumnType, ObjectID, XRotation, YRotation, ZRotation, XPosition, YPosition, ZPosition, Attribute1, Attribute2, Attribute3};
+use obj_attrs::AttrTable;
+use columns::{Column, ColumnField, ColumnLayout, FIELDS};
+use settings::Settings;
+use self::file_kind::FileKind;
+use self::clipboard::ObjectClipboard;
+use self::document::Document;
+use self::inspector::Inspector;
 
 const GLADE_SRC: &'static str = include_str!("gui.glade");
+/// The drag and drop target for set objects moved between tabs. The data is
+/// the same JSON the clipboard holds.
+const OBJECT_TARGET: &'static str = "application/x-sa2-set-objects";
 
-#[derive(Clone,Debug)]
+#[derive(Clone)]
 pub struct SetEditorGui {
-    set_objs: Rc<RefCell<SetFile>>,
+    /// The file given on the command line, opened in the first tab.
+    set_objs: Option<SetFile>,
     obj_table: Rc<RefCell<Option<ObjectTable>>>,
+    table_path: Option<PathBuf>,
+    /// Every open tab, in the order they were opened.
+    documents: Rc<RefCell<Vec<Document>>>,
+    /// The tab being shown, which the menus act on.
+    current: Rc<RefCell<Option<Document>>>,
+    /// The grid a drag of objects started from, so dropping them back where
+    /// they came from does nothing.
+    drag_source: Rc<RefCell<Option<TreeView>>>,
+    /// The column the search window matches against, kept so every tab's
+    /// grid searches the same way.
+    search_column: Rc<Cell<i32>>,
+    /// Which columns the grids show, in what order and format.
+    columns: Rc<RefCell<ColumnLayout>>,
+    /// What's remembered between launches. The column layout and window
+    /// size are copied in when it's saved.
+    settings: Rc<RefCell<Settings>>,
+    inspector: Inspector,
 }
 
 impl SetEditorGui {
-    pub fn new(set_objs: Option<SetFile>) -> SetEditorGui {
+    pub fn new(set_objs: Option<SetFile>, table_path: Option<PathBuf>) -> SetEditorGui {
         SetEditorGui {
-            set_objs: Rc::new(RefCell::new(set_objs.unwrap_or(SetFile(Vec::new())))),
+            set_objs,
             obj_table: Rc::new(RefCell::new(None)),
+            table_path,
+            documents: Rc::new(RefCell::new(Vec::new())),
+            current: Rc::new(RefCell::new(None)),
+            drag_source: Rc::new(RefCell::new(None)),
+            search_column: Rc::new(Cell::new(-1)),
+            columns: Rc::new(RefCell::new(ColumnLayout::default())),
+            settings: Rc::new(RefCell::new(Settings::default())),
+            inspector: Inspector::new(),
         }
     }
 
@@ -35,45 +77,68 @@ impl SetEditorGui {
         let builder = Builder::new();
         builder.add_from_string(GLADE_SRC).unwrap();
 
-        // TODO: set selectionmode to single for TreeSelection
-        let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-        set_grid.set_headers_clickable(true);
-        set_grid.set_property_enable_grid_lines(TreeViewGridLines::Both);
-
-        let set_list: ListStore = builder.get_object("Set Objects").unwrap();
-        let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
-
-        let mut columns = set_grid.get_columns().into_iter();
-        columns.next().unwrap().set_sort_column_id(0); // Index
-        self.connect_renderer::<ObjectID>(columns.next().unwrap(), 1, &set_list, &level_adjustment);
-        columns.next().unwrap().set_sort_column_id(2); // Object Name
-        self.connect_renderer::<XRotation>(columns.next().unwrap(), 3, &set_list, &level_adjustment);
-        self.connect_renderer::<YRotation>(columns.next().unwrap(), 4, &set_list, &level_adjustment);
-        self.connect_renderer::<ZRotation>(columns.next().unwrap(), 5, &set_list, &level_adjustment);
-        self.connect_renderer::<XPosition>(columns.next().unwrap(), 6, &set_list, &level_adjustment);
-        self.connect_renderer::<YPosition>(columns.next().unwrap(), 7, &set_list, &level_adjustment);
-        self.connect_renderer::<ZPosition>(columns.next().unwrap(), 8, &set_list, &level_adjustment);
-        self.connect_renderer::<Attribute1>(columns.next().unwrap(), 9, &set_list, &level_adjustment);
-        self.connect_renderer::<Attribute2>(columns.next().unwrap(), 10, &set_list, &level_adjustment);
-        self.connect_renderer::<Attribute3>(columns.next().unwrap(), 11, &set_list, &level_adjustment);
-        self.connect_menu(&builder);
+        let window: Window = builder.get_object("Set Editor").unwrap();
 
         let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
         let obj_table_id = statusbar.get_context_id("Object Table Info");
-        match ObjectTable::from_file(&PathBuf::from("obj_table.json")) {
-            Ok(obj_table) => {
-                statusbar.push(obj_table_id, "Successfully loaded object table file.");
+        match ObjectTable::load(self.table_path.as_deref()) {
+            Ok((obj_table, source)) => {
+                statusbar.push(obj_table_id, &format!("Loaded object table from {}.", source));
                 *self.obj_table.borrow_mut() = Some(obj_table);
             }
             Err(e) => {
-                statusbar.push(obj_table_id, &format!("Error loading object table: {}", e));
+                statusbar.push(obj_table_id, &format!("Error loading object table: {}. Using the built-in table.", e));
+                *self.obj_table.borrow_mut() = Some(ObjectTable::builtin());
             }
         }
 
-        let window: Window = builder.get_object("Set Editor").unwrap();
-        window.connect_delete_event(|_, _| {
-            gtk::main_quit();
-            Inhibit(false)
+        let attr_table_id = statusbar.get_context_id("Attribute Data Info");
+        match AttrTable::load(None) {
+            Ok((attr_table, source)) => {
+                statusbar.push(attr_table_id, &format!("Loaded attribute data from {}.", source));
+                *self.inspector.attr_table.borrow_mut() = attr_table;
+            }
+            Err(e) => {
+                statusbar.push(attr_table_id, &format!("Error loading attribute data: {}. Using the built-in data.", e));
+                *self.inspector.attr_table.borrow_mut() = AttrTable::builtin();
+            }
+        }
+
+        let settings_id = statusbar.get_context_id("Settings Info");
+        match Settings::load() {
+            Ok(settings) => *self.settings.borrow_mut() = settings,
+            Err(e) => {
+                statusbar.push(settings_id, &format!("Error loading settings: {}. Using the defaults.", e));
+            }
+        }
+        {
+            let settings = self.settings.borrow();
+            let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
+            level_adjustment.set_value(settings.level as f64);
+            window.set_default_size(settings.window_width, settings.window_height);
+            *self.columns.borrow_mut() = settings.columns.clone();
+        }
+
+        let set_objs = self.set_objs.take().unwrap_or(SetFile(Vec::new()));
+        self.connect_notebook(&builder);
+        self.connect_menu(&builder);
+        self.connect_columns_menu(&builder);
+        self.connect_settings_menu(&builder);
+        self.new_document(&builder, set_objs);
+
+        let self_clone = self.clone();
+        let builder_clone = builder.clone();
+        window.connect_delete_event(move |window, _| {
+            if self_clone.confirm_quit(window) {
+                if let Err(e) = self_clone.save_settings(&builder_clone) {
+                    eprintln!("Error saving settings: {}", e);
+                }
+                gtk::main_quit();
+                Inhibit(false)
+            }
+            else {
+                Inhibit(true)
+            }
         });
         window.show_all();
 
@@ -81,79 +146,421 @@ impl SetEditorGui {
         Ok(())
     }
 
-    fn load_file(&self, filename: &Path, set_list: &ListStore, level_adjustment: &Adjustment) -> Result<(), &'static str> {
-        let mut file = File::open(filename).map_err(|_| "Could not open file.")?;
-        let set_objs = SetFile::from_read::<Pc, _>(&mut file).map_err(|_| "Could not parse set file.")?;
+    fn current(&self) -> Option<Document> {
+        self.current.borrow().clone()
+    }
 
-        *self.set_objs.borrow_mut() = set_objs;
+    /// Opens `set_objs` in a new tab and switches to it.
+    fn new_document(&self, builder: &Builder, set_objs: SetFile) -> Document {
+        let notebook: Notebook = builder.get_object("Documents").unwrap();
+        let window: Window = builder.get_object("Set Editor").unwrap();
+        let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
+        let doc = Document::new(set_objs, level_adjustment.get_value() as u16, &self.obj_table, &self.columns, &notebook, &window);
+        self.documents.borrow_mut().push(doc.clone());
 
-        self.update_grid(set_list, level_adjustment);
-        Ok(())
+        {
+            let builder = builder.clone();
+            let self_clone = self.clone();
+            let doc_clone = doc.clone();
+            doc.close_button.connect_clicked(move |_| {
+                if doc_clone.confirm_discard() {
+                    self_clone.close_document(&builder, &doc_clone);
+                }
+            });
+        }
+
+        self.connect_drag(builder, &doc);
+        self.inspector.connect(builder, &doc);
+
+        notebook.set_current_page(notebook.page_num(&doc.page));
+        self.show_document(builder, &doc);
+        doc
     }
 
-    fn save_file(set_objs: &Rc<RefCell<SetFile>>, filename: &Path) -> Result<(), &'static str> {
-        let mut set_file = File::create(filename).map_err(|_| "Could not create set file.")?;
-        set_objs.borrow_mut().write_data::<Pc, _>(&mut set_file).map_err(|_| "Could not write set data.")?;
-        Ok(())
+    /// Makes `doc` the one the menus act on and shows its level and title.
+    /// The notebook's first page is switched to before its document is
+    /// known, so this is called directly as well as on page switches.
+    fn show_document(&self, builder: &Builder, doc: &Document) {
+        let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
+        let search_entry: Entry = builder.get_object("Search Entry").unwrap();
+        let window: Window = builder.get_object("Set Editor").unwrap();
+        *self.current.borrow_mut() = Some(doc.clone());
+
+        // During a page switch the notebook still shows the old page, so the
+        // title is set here rather than by the document.
+        window.set_title(&doc.window_title());
+        level_adjustment.set_value(doc.level.get() as f64);
+        doc.apply_columns();
+        doc.set_grid.set_search_entry(&search_entry);
+        if self.search_column.get() >= 0 {
+            doc.set_grid.set_search_column(self.search_column.get());
+        }
+        self.inspector.show(builder, doc);
     }
 
-    fn update_grid(&self, set_list: &ListStore, level_adjustment: &Adjustment) {
-        set_list.clear();
-    
-        let level_id = level_adjustment.get_value() as u16;
-        let mut index = 0;
-        for obj in self.set_objs.borrow_mut().0.iter() {
-            let empty = String::from("");
-            let obj_id = format!("{:04X}", obj.object.0);
-            let obj_table_borrow = self.obj_table.borrow();
-            let obj_name = obj_table_borrow.as_ref().and_then(|ot| ot.lookup(level_id, obj.object.0)).unwrap_or(&empty);
-            let rot_x = format!("{:04X}", obj.rotation.x);
-            let rot_y = format!("{:04X}", obj.rotation.y);
-            let rot_z = format!("{:04X}", obj.rotation.z);
-            let pos_x = obj.position.x;
-            let pos_y = obj.position.y;
-            let pos_z = obj.position.z;
-            let attr_1 = obj.attr1;
-            let attr_2 = obj.attr2;
-            let attr_3 = obj.attr3;
-    
-            set_list.insert_with_values(None, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], &[&index, &obj_id, &obj_name, &rot_x, &rot_y, &rot_z, &pos_x, &pos_y, &pos_z, &attr_1, &attr_2, &attr_3]);
-            index += 1;
+    /// Removes the tab for `doc` without asking about unsaved changes. A new
+    /// empty tab takes its place if it was the last one.
+    fn close_document(&self, builder: &Builder, doc: &Document) {
+        let notebook: Notebook = builder.get_object("Documents").unwrap();
+        self.documents.borrow_mut().retain(|open| open.page != doc.page);
+        notebook.remove_page(notebook.page_num(&doc.page));
+
+        if self.documents.borrow().is_empty() {
+            self.new_document(builder, SetFile(Vec::new()));
         }
     }
 
-    fn connect_renderer<T>(&self, column: TreeViewColumn, id: i32, set_list: &ListStore, level_adjustment: &Adjustment)
-        where T: ColumnType
-    {
-        // XXX: Technically should be fine because all renderers are CellRendererText,
-        // but downcasting is ugly.
-        let renderer: CellRendererText = column.get_cells()[0].clone().downcast().unwrap();
-        let set_list = set_list.clone();
-        let level_adjustment= level_adjustment.clone();
-        let self_clone = self.clone();
-        renderer.connect_edited(move |_, tree_path, text| {
-            if let Ok(value) = T::from_str(text) {
-                let iter = set_list.get_iter(&tree_path).unwrap();
-                let idx = set_list.get_value(&iter, 0).get::<u32>().unwrap() as usize;
-                value.update_obj(&self_clone.set_objs, idx);
-
-                let level_id = level_adjustment.get_value() as u16;
-                value.update_column(&set_list, &tree_path, &self_clone.obj_table, level_id);
+    /// Asks whether unsaved changes in any tab can be thrown away. Returns
+    /// true straight away if there aren't any.
+    fn confirm_quit(&self, window: &Window) -> bool {
+        let dirty: Vec<Document> = self.documents.borrow().iter()
+            .filter(|doc| doc.history.borrow().is_dirty())
+            .cloned()
+            .collect();
+        match dirty.len() {
+            0 => true,
+            1 => dirty[0].confirm_discard(),
+            count => {
+                let message = format!("{} files have unsaved changes. Discard them?", count);
+                let dialog = MessageDialog::new(Some(window), gtk::DIALOG_MODAL, MessageType::Question, ButtonsType::YesNo, &message);
+                let response = dialog.run();
+                dialog.destroy();
+                response == Into::<i32>::into(ResponseType::Yes)
             }
-        });
+        }
+    }
 
-        column.set_sort_column_id(id);
+    /// Adds a filter for each kind of file to `file_chooser`, returning them
+    /// so the chosen one can be matched back to its kind.
+    fn add_filters(file_chooser: &FileChooserDialog) -> Vec<(FileFilter, FileKind)> {
+        FileKind::ALL.iter().map(|&kind| {
+            let filter = FileFilter::new();
+            let name = format!("{}s ({})", kind, kind.pattern());
+            filter.set_name(Some(name.as_str()));
+            filter.add_pattern(&kind.pattern());
+            file_chooser.add_filter(&filter);
+            (filter, kind)
+        }).collect()
     }
 
-    fn connect_menu(&self, builder: &Builder) {
+    /// The kind picked in `file_chooser`, or the one `path`'s extension
+    /// suggests if no filter or a catch-all one was picked.
+    fn chosen_kind(&self, file_chooser: &FileChooserDialog, filters: &[(FileFilter, FileKind)], path: &Path) -> Option<FileKind> {
+        file_chooser.get_filter()
+            .and_then(|chosen| filters.iter().find(|&&(ref filter, _)| *filter == chosen).map(|&(_, kind)| kind))
+            .or_else(|| FileKind::from_path(path, self.settings.borrow().default_platform))
+    }
+
+    /// Opens `path` as `kind`, in the current tab if it's new and untouched
+    /// and otherwise in a new one, and remembers it as a recent file.
+    fn open_file(&self, builder: &Builder, path: &Path, kind: Option<FileKind>) {
+        let notebook: Notebook = builder.get_object("Documents").unwrap();
+        let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+        let open_id = statusbar.get_context_id("Open Info");
+
+        // An untouched new tab is reused rather than left lying around empty.
+        let (doc, reused) = match self.current() {
+            Some(ref doc) if doc.is_pristine() => (doc.clone(), true),
+            _ => (self.new_document(builder, SetFile(Vec::new())), false),
+        };
+
+        let result = kind
+            .ok_or_else(|| Error::other("not a set file or a json, csv or toml file").in_file(path))
+            .and_then(|kind| doc.load(path, kind).map(|warning| (kind, warning)));
+        match result {
+            Ok((kind, warning)) => {
+                match warning {
+                    None => statusbar.push(open_id, &format!("Successfully opened file: {}", path.to_str().unwrap_or(""))),
+                    Some(warning) => statusbar.push(open_id, &format!("Opened file: {} (Warning: {})", path.to_str().unwrap_or(""), warning)),
+                };
+                self.add_recent(builder, path, kind);
+            }
+            Err(e) => {
+                statusbar.push(open_id, &format!("Error: {}", e));
+                if !reused {
+                    self.close_document(builder, &doc);
+                }
+            }
+        }
+
+        if doc.page.get_parent().is_some() {
+            notebook.set_current_page(notebook.page_num(&doc.page));
+            self.show_document(builder, &doc);
+        }
+    }
+
+    /// Puts `path` at the top of File > Open Recent.
+    fn add_recent(&self, builder: &Builder, path: &Path, kind: FileKind) {
+        let platform = match kind {
+            FileKind::SetFile(platform) => Some(platform),
+            FileKind::Text(_) => None,
+        };
+        self.settings.borrow_mut().add_recent(path, platform);
+        self.update_recent_menu(builder);
+    }
+
+    fn update_recent_menu(&self, builder: &Builder) {
+        let open_recent: MenuItem = builder.get_object("Open Recent").unwrap();
+        let recent_menu: Menu = builder.get_object("Recent Menu").unwrap();
+        for child in recent_menu.get_children() {
+            recent_menu.remove(&child);
+        }
+
+        let recent_files = self.settings.borrow().recent_files.clone();
+        open_recent.set_sensitive(!recent_files.is_empty());
+        for recent in recent_files {
+            let name = recent.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let item = MenuItem::new_with_label(&name);
+            item.set_tooltip_text(Some(recent.path.to_string_lossy().as_ref()));
+            let builder = builder.clone();
+            let self_clone = self.clone();
+            item.connect_activate(move |_| {
+                let kind = match recent.platform {
+                    Some(platform) => Some(FileKind::SetFile(platform)),
+                    None => FileKind::from_path(&recent.path, self_clone.settings.borrow().default_platform),
+                };
+                self_clone.open_file(&builder, &recent.path, kind);
+            });
+            recent_menu.append(&item);
+        }
+        recent_menu.show_all();
+    }
+
+    /// Starts `file_chooser` where the last file was opened or saved.
+    fn start_in_last_dir(&self, file_chooser: &FileChooserDialog) {
+        if let Some(ref dir) = self.settings.borrow().last_dir {
+            file_chooser.set_current_folder(dir);
+        }
+    }
+
+    /// Sets up the menus that only change settings, and the recent files.
+    fn connect_settings_menu(&self, builder: &Builder) {
+        let platforms = [
+            ("Default PC", PlatformKind::Pc),
+            ("Default GameCube", PlatformKind::GameCube),
+            ("Default Dreamcast", PlatformKind::Dreamcast),
+        ];
+        let default_platform = self.settings.borrow().default_platform;
+        for &(name, platform) in platforms.iter() {
+            let item: RadioMenuItem = builder.get_object(name).unwrap();
+            item.set_active(platform == default_platform);
+            let self_clone = self.clone();
+            item.connect_toggled(move |item| {
+                if item.get_active() {
+                    self_clone.settings.borrow_mut().default_platform = platform;
+                }
+            });
+        }
+
+        self.update_recent_menu(builder);
+    }
+
+    /// Writes out what's remembered for next time: the level, window size,
+    /// column layout and widths, along with the recent files and platform
+    /// kept as they change.
+    fn save_settings(&self, builder: &Builder) -> error::Result<()> {
         let window: Window = builder.get_object("Set Editor").unwrap();
+        let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
+        if let Some(doc) = self.current() {
+            doc.store_widths();
+        }
+
+        let mut settings = self.settings.borrow_mut();
+        let (width, height) = window.get_size();
+        settings.window_width = width;
+        settings.window_height = height;
+        settings.level = level_adjustment.get_value() as u16;
+        settings.columns = self.columns.borrow().clone();
+        settings.save()
+    }
+
+    /// The entry being typed in, if any. Clipboard shortcuts go to it instead
+    /// of the grid, and undo and redo hold off until it's left.
+    fn focused_entry(window: &Window) -> Option<Entry> {
+        window.get_focus().and_then(|widget| widget.downcast::<Entry>().ok())
+    }
+
+    /// Keeps `current`, the level and the search window following the tab
+    /// being shown, and the tab's level following the level spinner.
+    fn connect_notebook(&self, builder: &Builder) {
+        {
+            let notebook: Notebook = builder.get_object("Documents").unwrap();
+            let builder = builder.clone();
+            let self_clone = self.clone();
+            notebook.connect_switch_page(move |_, page, _| {
+                let switched = self_clone.documents.borrow().iter()
+                    .find(|doc| doc.page.clone().upcast::<Widget>() == *page)
+                    .cloned();
+                if let Some(doc) = switched {
+                    self_clone.show_document(&builder, &doc);
+                }
+            });
+        }
 
         {
-            let open: MenuItem = builder.get_object("Open").unwrap();
-            let set_list: ListStore = builder.get_object("Set Objects").unwrap();
             let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
+            let self_clone = self.clone();
+            level_adjustment.connect_value_changed(move |adj| {
+                if let Some(doc) = self_clone.current() {
+                    let level = adj.get_value() as u16;
+                    if doc.level.get() != level {
+                        doc.set_level(level);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Lets objects be dragged from `doc`'s grid and dropped on another tab's,
+    /// which copies them there. Holding a drag over a tab switches to it.
+    fn connect_drag(&self, builder: &Builder, doc: &Document) {
+        let targets = vec![TargetEntry::new(OBJECT_TARGET, gtk::TARGET_SAME_APP, 0)];
+        doc.set_grid.drag_source_set(gdk::BUTTON1_MASK, &targets, gdk::ACTION_COPY);
+        doc.set_grid.drag_dest_set(gtk::DEST_DEFAULT_ALL, &targets, gdk::ACTION_COPY);
+
+        {
+            let self_clone = self.clone();
+            doc.set_grid.connect_drag_begin(move |set_grid, _| {
+                *self_clone.drag_source.borrow_mut() = Some(set_grid.clone());
+            });
+        }
+
+        {
+            let self_clone = self.clone();
+            doc.set_grid.connect_drag_end(move |_, _| {
+                *self_clone.drag_source.borrow_mut() = None;
+            });
+        }
+
+        {
             let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
-            let open_id = statusbar.get_context_id("Open Info");
+            let drag_id = statusbar.get_context_id("Drag Info");
+            let doc_clone = doc.clone();
+            doc.set_grid.connect_drag_data_get(move |_, _, selection_data, _, _| {
+                let objs = doc_clone.model.borrow().objects(&doc_clone.selected_keys());
+                match clipboard::to_json(&objs) {
+                    Ok(json) => {
+                        selection_data.set(&Atom::intern(OBJECT_TARGET), 8, json.as_bytes());
+                    }
+                    Err(e) => {
+                        statusbar.push(drag_id, &format!("Error: {}", e));
+                    }
+                }
+            });
+        }
+
+        {
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let drag_id = statusbar.get_context_id("Drag Info");
+            let self_clone = self.clone();
+            let doc_clone = doc.clone();
+            doc.set_grid.connect_drag_data_received(move |set_grid, _, _, _, selection_data, _, _| {
+                if self_clone.drag_source.borrow().as_ref() == Some(set_grid) {
+                    return;
+                }
+
+                let text = String::from_utf8_lossy(&selection_data.get_data()).into_owned();
+                match doc_clone.with_names(|names| clipboard::from_json(&text, names)) {
+                    Ok(objs) => {
+                        let count = objs.len();
+                        doc_clone.paste_objects(objs, &Position::default());
+                        statusbar.push(drag_id, &format!("Dropped {} objects into {}.", count, doc_clone.name()));
+                    }
+                    Err(e) => {
+                        statusbar.push(drag_id, &format!("Error: {}", e));
+                    }
+                }
+            });
+        }
+    }
+
+    /// Fills View > Columns with a menu per field: whether it's shown and,
+    /// for fields that have a choice, the format it's shown in.
+    fn connect_columns_menu(&self, builder: &Builder) {
+        let columns_menu: Menu = builder.get_object("Columns Menu").unwrap();
+        for &field in FIELDS.iter() {
+            let column = self.columns.borrow().column(field);
+            let field_menu = Menu::new();
+
+            let shown = CheckMenuItem::new_with_label("Shown");
+            shown.set_active(column.visible);
+            {
+                let self_clone = self.clone();
+                shown.connect_toggled(move |shown| {
+                    let visible = shown.get_active();
+                    self_clone.change_column(field, |column| column.visible = visible);
+                });
+            }
+            field_menu.append(&shown);
+
+            let formats = field.formats();
+            if formats.len() > 1 {
+                field_menu.append(&SeparatorMenuItem::new());
+                let format_items: Vec<_> = formats.iter().map(|&format| {
+                    let item = CheckMenuItem::new_with_label(format.title());
+                    item.set_draw_as_radio(true);
+                    item.set_active(format == column.format);
+                    field_menu.append(&item);
+                    (format, item)
+                }).collect();
+
+                for &(format, ref item) in format_items.iter() {
+                    let others: Vec<CheckMenuItem> = format_items.iter()
+                        .filter(|&&(other, _)| other != format)
+                        .map(|&(_, ref other)| other.clone())
+                        .collect();
+                    let self_clone = self.clone();
+                    item.connect_toggled(move |item| {
+                        if item.get_active() {
+                            self_clone.change_column(field, |column| column.format = format);
+                            for other in others.iter() {
+                                other.set_active(false);
+                            }
+                        }
+                        // Clicking the ticked format again shouldn't leave
+                        // none ticked.
+                        else if self_clone.columns.borrow().column(field).format == format {
+                            item.set_active(true);
+                        }
+                    });
+                }
+            }
+
+            let field_item = MenuItem::new_with_label(field.title());
+            field_item.set_submenu(Some(&field_menu));
+            columns_menu.append(&field_item);
+        }
+        columns_menu.show_all();
+    }
+
+    /// Changes how `field`'s column is shown in every tab. Tabs other than
+    /// the current one catch up when they're switched to.
+    fn change_column<F>(&self, field: ColumnField, change: F)
+        where F: FnOnce(&mut Column)
+    {
+        if let Some(column) = self.columns.borrow_mut().column_mut(field) {
+            change(column);
+        }
+        if let Some(doc) = self.current() {
+            doc.apply_columns();
+        }
+    }
+
+    fn connect_menu(&self, builder: &Builder) {
+        let window: Window = builder.get_object("Set Editor").unwrap();
+
+        {
+            let new: MenuItem = builder.get_object("New").unwrap();
+            let builder = builder.clone();
+            let self_clone = self.clone();
+            new.connect_activate(move |_| {
+                self_clone.new_document(&builder, SetFile(Vec::new()));
+            });
+        }
+
+        {
+            let open: MenuItem = builder.get_object("Open").unwrap();
+            let builder = builder.clone();
             let self_clone = self.clone();
             let window = window.clone();
             open.connect_activate(move |_| {
@@ -161,18 +568,21 @@ impl SetEditorGui {
                 file_chooser.add_button("_Cancel", ResponseType::Cancel.into());
                 file_chooser.add_button("_Open", ResponseType::Accept.into());
 
+                let all_files = FileFilter::new();
+                all_files.set_name(Some("All set and text files"));
+                for kind in FileKind::ALL.iter() {
+                    all_files.add_pattern(&kind.pattern());
+                }
+                file_chooser.add_filter(&all_files);
+                let filters = Self::add_filters(&file_chooser);
+                self_clone.start_in_last_dir(&file_chooser);
+
                 let response = file_chooser.run();
 
                 if response == Into::<i32>::into(ResponseType::Accept) {
                     if let Some(path) = file_chooser.get_filename() {
-                        match self_clone.load_file(&path, &set_list, &level_adjustment) {
-                            Ok(_) => {
-                                statusbar.push(open_id, &format!("Successfully opened file: {}", path.to_str().unwrap_or("")));
-                            }
-                            Err(e) => {
-                                statusbar.push(open_id, &format!("Error: {}", e));
-                            }
-                        }
+                        let kind = self_clone.chosen_kind(&file_chooser, &filters, &path);
+                        self_clone.open_file(&builder, &path, kind);
                     }
                 }
 
@@ -181,27 +591,84 @@ impl SetEditorGui {
         }
 
         {
-            let save: MenuItem = builder.get_object("Save As").unwrap();
+            let save: MenuItem = builder.get_object("Save").unwrap();
+            let save_as: MenuItem = builder.get_object("Save As").unwrap();
             let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
             let save_id = statusbar.get_context_id("Save Info");
-            let set_objs = self.set_objs.clone();
-            let window = window.clone();
+            let builder = builder.clone();
+            let self_clone = self.clone();
             save.connect_activate(move |_| {
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                // A new file has nowhere to go yet, so ask like Save As does.
+                let file_path = doc.file_path.borrow().clone();
+                let path = match file_path {
+                    Some(path) => path,
+                    None => {
+                        save_as.activate();
+                        return;
+                    }
+                };
+
+                let kind = doc.file_kind.get();
+                match doc.save(&path, kind) {
+                    Ok(_) => {
+                        statusbar.push(save_id, &format!("Successfully saved file: {} ({})", path.to_str().unwrap_or(""), kind));
+                        self_clone.add_recent(&builder, &path, kind);
+                    }
+                    Err(e) => {
+                        statusbar.push(save_id, &format!("Error: {}", e));
+                    }
+                }
+            });
+        }
+
+        {
+            let save_as: MenuItem = builder.get_object("Save As").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let save_id = statusbar.get_context_id("Save Info");
+            let builder = builder.clone();
+            let self_clone = self.clone();
+            let window = window.clone();
+            save_as.connect_activate(move |_| {
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
                 let file_chooser = FileChooserDialog::new(Some("Save File"), Some(&window), FileChooserAction::Save);
-                // TODO: Set current name based on type of input
-                // TODO: Set file filter
                 file_chooser.set_do_overwrite_confirmation(true);
                 file_chooser.add_button("_Cancel", ResponseType::Cancel.into());
                 file_chooser.add_button("_Save", ResponseType::Accept.into());
 
+                let filters = Self::add_filters(&file_chooser);
+                let current_kind = doc.file_kind.get();
+                if let Some(&(ref filter, _)) = filters.iter().find(|&&(_, kind)| kind == current_kind) {
+                    file_chooser.set_filter(filter);
+                }
+                match *doc.file_path.borrow() {
+                    Some(ref path) => {
+                        file_chooser.set_filename(path);
+                    }
+                    None => self_clone.start_in_last_dir(&file_chooser),
+                }
+
                 let response = file_chooser.run();
 
                 if response == Into::<i32>::into(ResponseType::Accept) {
-                    if let Some(path) = file_chooser.get_filename() {
-                        // TODO: error handling
-                        match Self::save_file(&set_objs, &path) {
+                    if let Some(mut path) = file_chooser.get_filename() {
+                        let kind = self_clone.chosen_kind(&file_chooser, &filters, &path).unwrap_or(current_kind);
+                        if path.extension().is_none() {
+                            path.set_extension(kind.extension());
+                        }
+
+                        match doc.save(&path, kind) {
                             Ok(_) => {
-                                statusbar.push(save_id, &format!("Successfully saved file: {}", path.to_str().unwrap_or("")));
+                                statusbar.push(save_id, &format!("Successfully saved file: {} ({})", path.to_str().unwrap_or(""), kind));
+                                self_clone.add_recent(&builder, &path, kind);
                             }
                             Err(e) => {
                                 statusbar.push(save_id, &format!("Error: {}", e));
@@ -215,153 +682,348 @@ impl SetEditorGui {
         }
 
         {
-            let add_object: MenuItem = builder.get_object("Add Object").unwrap();
-            let set_list: ListStore = builder.get_object("Set Objects").unwrap();
-            let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
+            let close_tab: MenuItem = builder.get_object("Close Tab").unwrap();
+            let builder = builder.clone();
             let self_clone = self.clone();
-            add_object.connect_activate(move |_| {
-                let (paths, model) = set_grid.get_selection().get_selected_rows();
-                let iter_opt = paths.get(0).map(|path| model.get_iter(path).unwrap());
-                let object = SetObject::default();
-
-                let idx = iter_opt.map(|iter| set_list.get_value(&iter, 0).get::<u32>().unwrap() as usize + 1).unwrap_or(0);
-                self_clone.set_objs.borrow_mut().0.insert(idx, object);
-
-                self_clone.update_grid(&set_list, &level_adjustment);
+            close_tab.connect_activate(move |_| {
+                if let Some(doc) = self_clone.current() {
+                    if doc.confirm_discard() {
+                        self_clone.close_document(&builder, &doc);
+                    }
+                }
             });
         }
 
         {
-            let add_object: MenuItem = builder.get_object("Remove Object").unwrap();
-            let set_list: ListStore = builder.get_object("Set Objects").unwrap();
-            let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
+            let add_object: MenuItem = builder.get_object("Add Object").unwrap();
             let self_clone = self.clone();
             add_object.connect_activate(move |_| {
-                let (paths, _) = set_grid.get_selection().get_selected_rows();
-                for path in paths {
-                    let idx = path.get_indices()[0] as usize;
-                    self_clone.set_objs.borrow_mut().0.remove(idx);
-                }
+                if let Some(doc) = self_clone.current() {
+                    let after = doc.selected_keys().first().cloned();
+                    let (keys, edit) = doc.model.borrow_mut().insert_after(after, vec![SetObject::default()]);
+                    doc.perform(edit);
 
-                self_clone.update_grid(&set_list, &level_adjustment);
+                    doc.update_grid();
+                    doc.select_keys(&keys);
+                }
             });
         }
 
         {
-            let column_search: MenuItem = builder.get_object("Column Search").unwrap();
-            let search_window: Window = builder.get_object("Search Window").unwrap();
-            search_window.connect_delete_event(|sw, _| {
-                sw.hide();
-                Inhibit(true)
-            });
-            column_search.connect_activate(move |_| {
-                search_window.show_all();
+            let remove_object: MenuItem = builder.get_object("Remove Object").unwrap();
+            let self_clone = self.clone();
+            remove_object.connect_activate(move |_| {
+                if let Some(doc) = self_clone.current() {
+                    let edit = doc.model.borrow().remove(&doc.selected_keys());
+                    doc.perform(edit);
+
+                    doc.update_grid();
+                }
             });
         }
 
-        // Search dialog stuff
         {
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            let search_entry: Entry = builder.get_object("Search Entry").unwrap();
-            set_grid.set_search_entry(&search_entry);
-        }
+            let copy: MenuItem = builder.get_object("Copy").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let clipboard_id = statusbar.get_context_id("Clipboard Info");
+            let clipboard = ObjectClipboard::new(builder);
+            let window = window.clone();
+            let self_clone = self.clone();
+            copy.connect_activate(move |_| {
+                if let Some(entry) = Self::focused_entry(&window) {
+                    entry.copy_clipboard();
+                    return;
+                }
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
 
-        {
-            let index_radio_button: RadioButton = builder.get_object("Index Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            index_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(0);
+                let objs = doc.model.borrow().objects(&doc.selected_keys());
+                match clipboard.copy(&objs) {
+                    Ok(_) => {
+                        statusbar.push(clipboard_id, &format!("Copied {} objects.", objs.len()));
+                    }
+                    Err(e) => {
+                        statusbar.push(clipboard_id, &format!("Error: {}", e));
+                    }
+                }
             });
         }
 
         {
-            let object_id_radio_button: RadioButton = builder.get_object("Object ID Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            object_id_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(1);
+            let cut: MenuItem = builder.get_object("Cut").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let clipboard_id = statusbar.get_context_id("Clipboard Info");
+            let clipboard = ObjectClipboard::new(builder);
+            let window = window.clone();
+            let self_clone = self.clone();
+            cut.connect_activate(move |_| {
+                if let Some(entry) = Self::focused_entry(&window) {
+                    entry.cut_clipboard();
+                    return;
+                }
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let keys = doc.selected_keys();
+                let objs = doc.model.borrow().objects(&keys);
+                if let Err(e) = clipboard.copy(&objs) {
+                    statusbar.push(clipboard_id, &format!("Error: {}", e));
+                    return;
+                }
+
+                let edit = doc.model.borrow().remove(&keys);
+                doc.perform(edit);
+                doc.update_grid();
+                statusbar.push(clipboard_id, &format!("Cut {} objects.", objs.len()));
             });
         }
 
         {
-            let object_name_radio_button: RadioButton = builder.get_object("Object Name Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            object_name_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(2);
+            let paste: MenuItem = builder.get_object("Paste").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let clipboard_id = statusbar.get_context_id("Clipboard Info");
+            let clipboard = ObjectClipboard::new(builder);
+            let window = window.clone();
+            let self_clone = self.clone();
+            paste.connect_activate(move |_| {
+                if let Some(entry) = Self::focused_entry(&window) {
+                    entry.paste_clipboard();
+                    return;
+                }
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let offset = match clipboard.offset() {
+                    Some(offset) => offset,
+                    None => {
+                        statusbar.push(clipboard_id, "Paste offset values cannot be parsed as floats.");
+                        return;
+                    }
+                };
+
+                match doc.with_names(|names| clipboard.paste(names)) {
+                    Ok(objs) => {
+                        let count = objs.len();
+                        doc.paste_objects(objs, &offset);
+                        statusbar.push(clipboard_id, &format!("Pasted {} objects.", count));
+                    }
+                    Err(e) => {
+                        statusbar.push(clipboard_id, &format!("The clipboard doesn't hold set objects: {}", e));
+                    }
+                }
             });
         }
 
         {
-            let x_rotation_radio_button: RadioButton = builder.get_object("X Rotation Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            x_rotation_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(3);
+            let duplicate: MenuItem = builder.get_object("Duplicate").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let clipboard_id = statusbar.get_context_id("Clipboard Info");
+            let clipboard = ObjectClipboard::new(builder);
+            let self_clone = self.clone();
+            duplicate.connect_activate(move |_| {
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let offset = match clipboard.offset() {
+                    Some(offset) => offset,
+                    None => {
+                        statusbar.push(clipboard_id, "Paste offset values cannot be parsed as floats.");
+                        return;
+                    }
+                };
+
+                let objs = doc.model.borrow().objects(&doc.selected_keys());
+                let count = objs.len();
+                doc.paste_objects(objs, &offset);
+                statusbar.push(clipboard_id, &format!("Duplicated {} objects.", count));
             });
         }
 
         {
-            let y_rotation_radio_button: RadioButton = builder.get_object("Y Rotation Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            y_rotation_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(4);
+            let paste_offset: MenuItem = builder.get_object("Paste Offset").unwrap();
+            let paste_offset_window: Window = builder.get_object("Paste Offset Window").unwrap();
+            paste_offset_window.connect_delete_event(|pw, _| {
+                pw.hide();
+                Inhibit(true)
+            });
+            paste_offset.connect_activate(move |_| {
+                paste_offset_window.show_all();
             });
         }
 
         {
-            let z_rotation_radio_button: RadioButton = builder.get_object("Z Rotation Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            z_rotation_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(5);
+            let undo: MenuItem = builder.get_object("Undo").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let history_id = statusbar.get_context_id("History Info");
+            let window = window.clone();
+            let self_clone = self.clone();
+            undo.connect_activate(move |_| {
+                // Entries have no history of their own to step through, but
+                // undoing mustn't reach past them to the grid either. Menu
+                // clicks land here too, so say why nothing happened.
+                if Self::focused_entry(&window).is_some() {
+                    statusbar.push(history_id, "Nothing undone: leave the text field to undo changes to objects.");
+                    return;
+                }
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let description = doc.history.borrow_mut().undo(&mut doc.model.borrow_mut()).map(Edit::describe);
+                match description {
+                    Some(description) => {
+                        doc.update_grid();
+                        doc.update_title();
+                        statusbar.push(history_id, &format!("Undid {}.", description));
+                    }
+                    None => {
+                        statusbar.push(history_id, "Nothing to undo.");
+                    }
+                }
             });
         }
 
         {
-            let x_position_radio_button: RadioButton = builder.get_object("X Position Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            x_position_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(6);
+            let redo: MenuItem = builder.get_object("Redo").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let history_id = statusbar.get_context_id("History Info");
+            let window = window.clone();
+            let self_clone = self.clone();
+            redo.connect_activate(move |_| {
+                if Self::focused_entry(&window).is_some() {
+                    statusbar.push(history_id, "Nothing redone: leave the text field to redo changes to objects.");
+                    return;
+                }
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let description = doc.history.borrow_mut().redo(&mut doc.model.borrow_mut()).map(Edit::describe);
+                match description {
+                    Some(description) => {
+                        doc.update_grid();
+                        doc.update_title();
+                        statusbar.push(history_id, &format!("Redid {}.", description));
+                    }
+                    None => {
+                        statusbar.push(history_id, "Nothing to redo.");
+                    }
+                }
             });
         }
 
         {
-            let y_position_radio_button: RadioButton = builder.get_object("Y Position Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            y_position_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(7);
+            let transform_objects: MenuItem = builder.get_object("Transform Objects").unwrap();
+            let transform_window: Window = builder.get_object("Transform Window").unwrap();
+            transform_window.connect_delete_event(|tw, _| {
+                tw.hide();
+                Inhibit(true)
+            });
+            transform_objects.connect_activate(move |_| {
+                transform_window.show_all();
             });
         }
 
         {
-            let z_position_radio_button: RadioButton = builder.get_object("Z Position Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            z_position_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(8);
+            let transform_button: Button = builder.get_object("Transform Button").unwrap();
+            let transform_entry: Entry = builder.get_object("Transform Entry").unwrap();
+            let selected_only: CheckButton = builder.get_object("Transform Selected").unwrap();
+            let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
+            let transform_id = statusbar.get_context_id("Transform Info");
+            let self_clone = self.clone();
+            transform_button.connect_clicked(move |_| {
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
+                let text = transform_entry.get_text().unwrap_or_default();
+                let query = match Query::from_str(&text) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        statusbar.push(transform_id, &format!("Bad transform expression: {}", e));
+                        return;
+                    }
+                };
+
+                // Transform a copy and record each object that changed, so the
+                // whole transform is undone in one step.
+                let mut transformed = doc.model.borrow().set_file().clone();
+                let selected = doc.selected_keys();
+                let count = if selected_only.get_active() {
+                    let indices: Vec<usize> = {
+                        let model = doc.model.borrow();
+                        selected.iter().filter_map(|&key| model.index_of(key)).collect()
+                    };
+                    let mut chosen = SetFile(indices.iter().map(|&index| transformed.0[index]).collect());
+                    let count = query.apply(&mut chosen);
+                    for (&index, obj) in indices.iter().zip(chosen.0) {
+                        transformed.0[index] = obj;
+                    }
+                    count
+                }
+                else {
+                    query.apply(&mut transformed)
+                };
+                let edit = doc.model.borrow().replace_all(&transformed);
+                doc.perform(edit);
+
+                doc.update_grid();
+                doc.select_keys(&selected);
+                statusbar.push(transform_id, &format!("Transformed {} objects.", count));
             });
         }
 
         {
-            let attribute_1_radio_button: RadioButton = builder.get_object("Attribute 1 Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            attribute_1_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(9);
+            let fit_map: MenuItem = builder.get_object("Fit Map").unwrap();
+            let self_clone = self.clone();
+            fit_map.connect_activate(move |_| {
+                if let Some(doc) = self_clone.current() {
+                    *doc.viewport.borrow_mut() = None;
+                    doc.map_view.queue_draw();
+                }
             });
         }
 
         {
-            let attribute_2_radio_button: RadioButton = builder.get_object("Attribute 2 Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            attribute_2_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(10);
+            let column_search: MenuItem = builder.get_object("Column Search").unwrap();
+            let search_window: Window = builder.get_object("Search Window").unwrap();
+            search_window.connect_delete_event(|sw, _| {
+                sw.hide();
+                Inhibit(true)
+            });
+            column_search.connect_activate(move |_| {
+                search_window.show_all();
             });
         }
 
+        // Search dialog stuff. The entry is handed to each tab's grid as it's
+        // switched to, and the column picked here goes with it.
         {
-            let attribute_3_radio_button: RadioButton = builder.get_object("Attribute 3 Radio Button").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
-            attribute_3_radio_button.connect_clicked(move |_| {
-                set_grid.set_search_column(11);
+            let search_column: ComboBoxText = builder.get_object("Search Column").unwrap();
+            for &field in FIELDS.iter() {
+                search_column.append(Some(field.text_column().to_string().as_str()), field.title());
+            }
+            let self_clone = self.clone();
+            search_column.connect_changed(move |combo| {
+                if let Some(column) = combo.get_active_id().and_then(|id| id.parse::<i32>().ok()) {
+                    self_clone.search_column.set(column);
+                    if let Some(doc) = self_clone.current() {
+                        doc.set_grid.set_search_column(column);
+                    }
+                }
             });
+            search_column.set_active(0);
         }
 
         {
@@ -378,58 +1040,61 @@ impl SetEditorGui {
 
         {
             let point_search_button: Button = builder.get_object("Point Search Button").unwrap();
-            let set_list: ListStore = builder.get_object("Set Objects").unwrap();
-            let set_grid: TreeView = builder.get_object("Set Grid").unwrap();
             let x_position_entry: Entry = builder.get_object("X Position Entry").unwrap();
             let y_position_entry: Entry = builder.get_object("Y Position Entry").unwrap();
             let z_position_entry: Entry = builder.get_object("Z Position Entry").unwrap();
+            let radius_entry: Entry = builder.get_object("Radius Entry").unwrap();
             let statusbar: Statusbar = builder.get_object("Status Bar").unwrap();
             let search_id = statusbar.get_context_id("Search Info");
+            let self_clone = self.clone();
             point_search_button.connect_clicked(move |_| {
+                let doc = match self_clone.current() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+
                 let position_opt = x_position_entry.get_text().and_then(|text| f32::from_str(&text).ok())
                     .and_then(|x| y_position_entry.get_text().and_then(|text| f32::from_str(&text).ok()).map(|y| (x, y)))
                     .and_then(|(x, y)| z_position_entry.get_text().and_then(|text| f32::from_str(&text).ok()).map(|z| (x, y, z)));
+                let radius_text = radius_entry.get_text().unwrap_or_default();
 
-                if let Some((x, y, z)) = position_opt {
-                    let mut closest: Option<(f32, TreeIter)> = None;
-
-                    let mut iter = set_list.get_iter_first();
-                    while let Some(row) = iter {
-                        let row_x = set_list.get_value(&row, 6).get::<f32>().unwrap();
-                        let row_y = set_list.get_value(&row, 7).get::<f32>().unwrap();
-                        let row_z = set_list.get_value(&row, 8).get::<f32>().unwrap();
-
-                        let distance_squared = (row_x - x) * (row_x - x) + (row_y - y) * (row_y - y) + (row_z - z) * (row_z - z);
-                        if closest.is_none() || distance_squared < closest.as_ref().unwrap().0 {
-                            closest = Some((distance_squared, row.clone()));
-                        }
+                let (x, y, z) = match position_opt {
+                    Some(position) => position,
+                    None => {
+                        statusbar.push(search_id, "Position values cannot be parsed as floats.");
+                        return;
+                    }
+                };
+                let point = Position { x: x, y: y, z: z };
 
-                        if set_list.iter_next(&row) {
-                            iter = Some(row);
-                        }
-                        else {
-                            iter = None;
+                let index = SpatialIndex::new(doc.model.borrow().set_file());
+                let found = if radius_text.trim().is_empty() {
+                    index.nearest(&point, 1)
+                }
+                else {
+                    match f32::from_str(radius_text.trim()) {
+                        Ok(radius) => index.within_radius(&point, radius),
+                        Err(_) => {
+                            statusbar.push(search_id, "Radius cannot be parsed as a float.");
+                            return;
                         }
                     }
+                };
 
-                    if let Some((_, iter)) = closest {
-                        set_grid.get_selection().select_iter(&iter);
-                        let path = set_list.get_path(&iter).unwrap();
-                        set_grid.set_cursor(&path, None, false);
+                let keys: Vec<ObjectKey> = {
+                    let model = doc.model.borrow();
+                    found.iter().filter_map(|&(idx, _)| model.key_at(idx)).collect()
+                };
+                doc.select_keys(&keys);
+
+                match found.first() {
+                    Some(&(nearest_idx, distance)) => {
+                        statusbar.push(search_id, &format!("Found {} objects. Nearest is object {} at distance {}.", found.len(), nearest_idx, distance));
+                    }
+                    None => {
+                        statusbar.push(search_id, "No objects found.");
                     }
                 }
-                else {
-                    statusbar.push(search_id, "Position values cannot be parsed as floats.");
-                }
-            });
-        }
-
-        {
-            let set_list: ListStore = builder.get_object("Set Objects").unwrap();
-            let level_adjustment: Adjustment = builder.get_object("Level Adjustment").unwrap();
-            let self_clone = self.clone();
-            level_adjustment.connect_value_changed(move |adj| {
-                self_clone.update_grid(&set_list, &adj);
             });
         }
     }
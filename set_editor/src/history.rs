@@ -0,0 +1,168 @@
+use sa2_set::SetObject;
+
+use set_model::{ObjectKey, SetModel};
+
+/// One reversible change to a set file. Indices are those at the time the
+/// edit is applied or reverted, which always match because edits are undone
+/// and redone in order.
+#[derive(Clone,Debug,PartialEq)]
+pub enum Edit {
+    /// Replaces the object at `index`, as editing a cell does.
+    Replace { index: usize, old: SetObject, new: SetObject },
+    Insert { index: usize, key: ObjectKey, obj: SetObject },
+    Remove { index: usize, key: ObjectKey, obj: SetObject },
+    /// Edits that are done and undone together, applied in order.
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    pub fn apply(&self, model: &mut SetModel) {
+        match *self {
+            Edit::Replace { index, ref new, .. } => model.replace_at(index, *new),
+            Edit::Insert { index, key, ref obj } => model.insert_at(index, key, *obj),
+            Edit::Remove { index, .. } => model.remove_at(index),
+            Edit::Group(ref edits) => {
+                for edit in edits.iter() {
+                    edit.apply(model);
+                }
+            }
+        }
+    }
+
+    /// Undoes the edit. Removed objects come back with the key they had.
+    pub fn revert(&self, model: &mut SetModel) {
+        match *self {
+            Edit::Replace { index, ref old, .. } => model.replace_at(index, *old),
+            Edit::Insert { index, .. } => model.remove_at(index),
+            Edit::Remove { index, key, ref obj } => model.insert_at(index, key, *obj),
+            Edit::Group(ref edits) => {
+                for edit in edits.iter().rev() {
+                    edit.revert(model);
+                }
+            }
+        }
+    }
+
+    /// A short description for the status bar.
+    pub fn describe(&self) -> String {
+        match *self {
+            Edit::Replace { index, .. } => format!("edit of object {}", index),
+            Edit::Insert { index, .. } => format!("insertion of object {}", index),
+            Edit::Remove { index, .. } => format!("removal of object {}", index),
+            Edit::Group(ref edits) if edits.len() == 1 => edits[0].describe(),
+            Edit::Group(ref edits) => format!("change to {} objects", edits.len()),
+        }
+    }
+}
+
+/// The undo and redo stacks for one set file, and whether it has changed
+/// since it was last saved.
+#[derive(Clone,Debug,Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// How many edits were on the undo stack when the file was saved, or
+    /// `None` if that state can't be reached by undoing or redoing anymore.
+    saved_at: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved_at: Some(0),
+        }
+    }
+
+    /// Applies `edit` and records it. Anything that could be redone is
+    /// dropped.
+    pub fn perform(&mut self, model: &mut SetModel, edit: Edit) {
+        if let Edit::Group(ref edits) = edit {
+            if edits.is_empty() {
+                return;
+            }
+        }
+
+        edit.apply(model);
+        if self.saved_at.map(|saved_at| saved_at > self.undo.len()).unwrap_or(false) {
+            self.saved_at = None;
+        }
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Reverts the last edit, returning it.
+    pub fn undo(&mut self, model: &mut SetModel) -> Option<&Edit> {
+        let edit = self.undo.pop()?;
+        edit.revert(model);
+        self.redo.push(edit);
+        self.redo.last()
+    }
+
+    /// Applies the last undone edit again, returning it.
+    pub fn redo(&mut self, model: &mut SetModel) -> Option<&Edit> {
+        let edit = self.redo.pop()?;
+        edit.apply(model);
+        self.undo.push(edit);
+        self.undo.last()
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_at = Some(self.undo.len());
+    }
+
+    /// Whether there are changes since the file was last saved or opened.
+    pub fn is_dirty(&self) -> bool {
+        self.saved_at != Some(self.undo.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetFile, SetObject, Object};
+
+    use set_model::{ObjectKey, SetModel};
+    use super::{Edit, History};
+
+    fn obj(id: u16) -> SetObject {
+        SetObject {
+            object: Object(id),
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut model = SetModel::new(SetFile(vec![obj(0), obj(1), obj(2)]));
+        let original = model.set_file().clone();
+        let mut history = History::new();
+        assert!(!history.is_dirty());
+
+        history.perform(&mut model, Edit::Replace { index: 1, old: obj(1), new: obj(5) });
+        history.perform(&mut model, Edit::Group(vec![
+            Edit::Remove { index: 2, key: ObjectKey(2), obj: obj(2) },
+            Edit::Remove { index: 0, key: ObjectKey(0), obj: obj(0) },
+        ]));
+        history.perform(&mut model, Edit::Insert { index: 1, key: ObjectKey(3), obj: obj(7) });
+        assert_eq!(model.set_file().0, vec![obj(5), obj(7)]);
+        assert!(history.is_dirty());
+
+        assert_eq!(history.undo(&mut model).map(Edit::describe), Some(String::from("insertion of object 1")));
+        while history.undo(&mut model).is_some() {}
+        assert_eq!(model.set_file().0, original.0);
+        assert!(!history.is_dirty());
+
+        history.redo(&mut model);
+        assert_eq!(model.set_file().0, vec![obj(0), obj(5), obj(2)]);
+        history.mark_saved();
+        history.undo(&mut model);
+        assert!(history.is_dirty());
+
+        // A new edit makes the saved state unreachable.
+        history.perform(&mut model, Edit::Insert { index: 0, key: ObjectKey(4), obj: obj(9) });
+        assert!(history.redo(&mut model).is_none());
+        history.undo(&mut model);
+        assert!(history.is_dirty());
+    }
+}
@@ -5,28 +5,68 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate csv;
+extern crate toml;
+extern crate dirs;
+extern crate rayon;
+extern crate walkdir;
+extern crate notify;
+extern crate prs_util;
 #[cfg(feature="gui")]
 extern crate gtk;
+#[cfg(feature="gui")]
+extern crate gdk;
+#[cfg(feature="gui")]
+extern crate cairo;
 
+mod error;
+mod batch;
+mod watch;
 mod obj_table;
+mod format;
+mod svg;
 #[cfg(windows)]
 mod windows_pretty_formatter;
 #[cfg(feature="gui")]
 mod gui;
+#[cfg(any(feature="gui", test))]
+mod history;
+#[cfg(any(feature="gui", test))]
+mod set_model;
+#[cfg(any(feature="gui", test))]
+mod map_view;
+#[cfg(any(feature="gui", test))]
+mod filter_expr;
+#[cfg(any(feature="gui", test))]
+#[cfg_attr(not(feature="gui"), allow(dead_code))]
+mod columns;
+#[cfg(any(feature="gui", test))]
+#[cfg_attr(not(feature="gui"), allow(dead_code))]
+mod settings;
+#[cfg(any(feature="gui", test))]
+#[cfg_attr(not(feature="gui"), allow(dead_code))]
+mod obj_attrs;
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
 
-use sa2_set::{SetFile, Platform, Dreamcast, GameCube, Pc};
+use prs_util::encoder::Encoder;
+use sa2_set::{SetFile, SetFileName, Query, SpatialIndex, Position, Platform, Dreamcast, GameCube, Pc, OBJECT_LIMIT};
 use serde::ser::Serialize;
 use serde_json::ser::Serializer;
 #[cfg(not(windows))]
 use serde_json::ser::PrettyFormatter;
 use getopts::Options;
 
+use format::Format;
+use svg::SvgOptions;
+use obj_table::ObjectTable;
+use error::{Error, ResultExt};
+
 #[cfg(windows)]
 const NEWLINE: &'static [u8] = b"\r\n";
 #[cfg(windows)]
@@ -43,10 +83,23 @@ const NO_ARGS_MEANS_GUI: bool = false;
 enum Mode {
     Encode,
     Decode,
+    Transform(String),
+    Search(Search),
+    Plot(PathBuf),
+    Batch(PathBuf),
+    Watch(PathBuf),
+    CheckTables,
+    MergeTables(PathBuf),
     Gui,
     Help,
 }
 
+enum Search {
+    Nearest(Position, usize),
+    Radius(Position, f32),
+    Box(Position, Position),
+}
+
 fn main() {
     let mut env_args = env::args();
     let program = env_args.next().unwrap();
@@ -58,6 +111,24 @@ fn main() {
     opts.optflag("h", "help", "print this help menu");
     opts.optflag("s", "single-line", "write objects on a single line");
     opts.optflag("g", "gui", "run in gui mode");
+    opts.optopt("t", "transform", "apply a query and transform expression to a set or text file", "EXPR");
+    opts.optopt("n", "near", "list the objects nearest to a point", "X,Y,Z");
+    opts.optopt("k", "count", "number of objects listed by --near (default: 1)", "N");
+    opts.optopt("r", "radius", "list every object within this distance of the --near point", "R");
+    opts.optopt("b", "box", "list every object inside a box", "X,Y,Z:X,Y,Z");
+    opts.optopt("p", "plot", "plot object positions to an svg image", "SVG");
+    opts.optopt("", "diff", "with --plot, highlight objects added, removed or moved since this file", "OLD");
+    opts.optflag("", "side-view", "with --plot, add a side view below the top-down view");
+    opts.optflag("", "labels", "with --plot, label objects with their names");
+    opts.optopt("f", "format", "text format to convert to or from (json, csv or toml; default: by extension)", "FORMAT");
+    opts.optopt("l", "level", "level id used for object names (default: from the set file name)", "LEVEL");
+    opts.optopt("o", "object-table", "object table to use instead of the one in the config directory, next to the program or built in", "TABLE");
+    opts.optflag("a", "annotate", "add object names to decoded json files");
+    opts.optopt("", "batch", "convert every set*.bin and json file in the inputs (files or directories) into a mirrored tree under OUTDIR", "OUTDIR");
+    opts.optopt("w", "watch", "re-encode text files in the input directory into GAMEDIR whenever they are saved", "GAMEDIR");
+    opts.optflag("", "prs", "prs-compress encoded set files");
+    opts.optflag("", "check-table", "check object table files for missing or ambiguous names");
+    opts.optopt("", "merge-tables", "merge object table files, later ones taking priority", "OUTPUT");
 
     let matches = match opts.parse(&args) {
         Ok(m) => m,
@@ -78,6 +149,55 @@ fn main() {
         };
     }
 
+    if let Some(expr) = matches.opt_str("t") {
+        mode = match mode {
+            None => Some(Mode::Transform(expr)),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if let Some(search) = parse_search(&matches) {
+        mode = match mode {
+            None => Some(Mode::Search(search)),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if let Some(svg_path) = matches.opt_str("p") {
+        mode = match mode {
+            None => Some(Mode::Plot(svg_path.into())),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if let Some(out_dir) = matches.opt_str("batch") {
+        mode = match mode {
+            None => Some(Mode::Batch(out_dir.into())),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if let Some(game_dir) = matches.opt_str("w") {
+        mode = match mode {
+            None => Some(Mode::Watch(game_dir.into())),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if matches.opt_present("check-table") {
+        mode = match mode {
+            None => Some(Mode::CheckTables),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
+    if let Some(output) = matches.opt_str("merge-tables") {
+        mode = match mode {
+            None => Some(Mode::MergeTables(output.into())),
+            Some(_) => barf("Only one action can be specified."),
+        };
+    }
+
     if matches.opt_present("g") {
         mode = Some(Mode::Gui);
     }
@@ -90,11 +210,19 @@ fn main() {
         single_line = true;
     }
 
+    let format = matches.opt_str("f").map(|f| Format::from_str(&f).unwrap_or_else(|e| barf(e)));
+    let level = matches.opt_str("l").map(|l| u16::from_str(&l).unwrap_or_else(|_| barf("Level must be a number.")));
+    let annotate = matches.opt_present("a");
+    let compress = matches.opt_present("prs");
+    let table_path = matches.opt_str("o").map(PathBuf::from);
+    let names = || Names::load(table_path.as_deref(), level);
+
     match mode {
         Some(Mode::Encode) => {
             let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
             let output: PathBuf = matches.free.get(1).unwrap_or_else(|| barf("No output file specified.")).into();
-            match encode_file::<GameCube>(&input, &output) {
+            let input_format = format.or_else(|| Format::from_path(&input)).unwrap_or(Format::Json);
+            match encode_file::<GameCube>(&input, &output, input_format, compress, &names()) {
                 Ok(_) => println!("Successfully encoded file."),
                 Err(e) => barf(&e.to_string()),
             }
@@ -102,45 +230,154 @@ fn main() {
         Some(Mode::Decode) => {
             let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
             let output: PathBuf = matches.free.get(1).unwrap_or_else(|| barf("No output file specified.")).into();
-            match decode_file::<GameCube>(&input, &output, single_line) {
+            let output_format = format.or_else(|| Format::from_path(&output)).unwrap_or(Format::Json);
+            match decode_file::<GameCube>(&input, &output, output_format, single_line, annotate, &names()) {
                 Ok(_) => println!("Successfully decoded file."),
                 Err(e) => barf(&e.to_string()),
             }
         }
+        Some(Mode::Transform(expr)) => {
+            let query = Query::from_str(&expr).unwrap_or_else(|e| barf(&format!("Bad transform expression: {}", e)));
+            let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
+            let output: PathBuf = matches.free.get(1).map(PathBuf::from).unwrap_or_else(|| input.clone());
+            match transform_file::<GameCube>(&input, &output, &query, format, single_line, annotate, &names()) {
+                Ok(count) => println!("Successfully transformed {} objects.", count),
+                Err(e) => barf(&e.to_string()),
+            }
+        }
+        Some(Mode::Search(search)) => {
+            let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
+            if let Err(e) = search_file::<GameCube>(&input, &search, format, &names()) {
+                barf(&e.to_string());
+            }
+        }
+        Some(Mode::Plot(svg_path)) => {
+            let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
+            let old = matches.opt_str("diff").map(PathBuf::from);
+            let options = SvgOptions {
+                side_view: matches.opt_present("side-view"),
+                labels: matches.opt_present("labels"),
+            };
+            match plot_file::<GameCube>(&input, old.as_deref(), &svg_path, format, &names(), &options) {
+                Ok(_) => println!("Successfully plotted file."),
+                Err(e) => barf(&e.to_string()),
+            }
+        }
+        Some(Mode::Batch(out_dir)) => {
+            if matches.free.is_empty() {
+                barf("No input file specified.");
+            }
+            let inputs: Vec<PathBuf> = matches.free.iter().map(PathBuf::from).collect();
+            let text_format = format.unwrap_or(Format::Json);
+            let jobs = batch::collect_jobs(&inputs, &out_dir, text_format, compress).unwrap_or_else(|e| barf(&e.to_string()));
+            let names = names();
+
+            let summary = batch::run(&jobs, |job| {
+                match job.action {
+                    batch::Action::Decode => decode_file::<GameCube>(&job.input, &job.output, text_format, single_line, annotate, &names),
+                    batch::Action::Encode => encode_file::<GameCube>(&job.input, &job.output, text_format, compress, &names),
+                }
+            });
+
+            for (_, e) in summary.failed.iter() {
+                println!("Error: {}", e);
+            }
+            println!("Decoded {} files, encoded {} files, {} failed.", summary.decoded, summary.encoded, summary.failed.len());
+            if !summary.failed.is_empty() {
+                process::exit(1);
+            }
+        }
+        Some(Mode::Watch(game_dir)) => {
+            let source = matches.free.get(0).unwrap_or_else(|| barf("No input directory specified."));
+            let source = fs::canonicalize(source).in_file(Path::new(source)).unwrap_or_else(|e| barf(&e.to_string()));
+            let text_format = format.unwrap_or(Format::Json);
+            let names = names();
+
+            println!("Watching {} for {} files. Press Ctrl+C to stop.", source.display(), text_format.extension());
+            let result = watch::watch(&source, text_format, |changed| {
+                let output = watch::output_path(&source, changed, &game_dir, compress);
+                let result = output.parent()
+                    .map_or(Ok(()), |parent| fs::create_dir_all(parent).in_file(parent))
+                    .and_then(|_| encode_file::<GameCube>(changed, &output, text_format, compress, &names));
+                match result {
+                    Ok(_) => println!("Encoded {} to {}.", changed.display(), output.display()),
+                    Err(e) => println!("Error: {}", e),
+                }
+            });
+            if let Err(e) = result {
+                barf(&e.to_string());
+            }
+        }
+        Some(Mode::CheckTables) => {
+            if matches.free.is_empty() {
+                barf("No object table specified.");
+            }
+            let mut problem_count = 0;
+            for input in matches.free.iter() {
+                let table = ObjectTable::from_file(Path::new(input)).unwrap_or_else(|e| barf(&e.to_string()));
+                for problem in table.validate() {
+                    println!("{}: {}", input, problem);
+                    problem_count += 1;
+                }
+            }
+            if problem_count > 0 {
+                barf(&format!("Found {} problems.", problem_count));
+            }
+            println!("No problems found.");
+        }
+        Some(Mode::MergeTables(output)) => {
+            if matches.free.is_empty() {
+                barf("No object table specified.");
+            }
+            let mut merged = ObjectTable::default();
+            for input in matches.free.iter() {
+                let table = ObjectTable::from_file(Path::new(input)).unwrap_or_else(|e| barf(&e.to_string()));
+                for note in merged.merge(&table) {
+                    println!("{}: {}", input, note);
+                }
+            }
+            let table_file = File::create(&output).in_file(&output).unwrap_or_else(|e| barf(&e.to_string()));
+            match merged.to_writer(table_file).in_file(&output) {
+                Ok(_) => println!("Successfully merged {} object tables.", matches.free.len()),
+                Err(e) => barf(&e.to_string()),
+            }
+        }
         Some(Mode::Help) => {
             print_usage(&program, opts);
             process::exit(0);
         }
         Some(Mode::Gui) => {
-            run_gui();
+            run_gui(table_path.as_deref());
         }
         None => {
             if matches.free.is_empty() && NO_ARGS_MEANS_GUI {
-                run_gui();
+                run_gui(table_path.as_deref());
             }
             else {
                 let input: PathBuf = matches.free.get(0).unwrap_or_else(|| barf("No input file specified.")).into();
                 match input.extension() {
                     Some(os_str) => {
                         match os_str.to_str() {
-                            Some("json") => {
-                                let output = input.with_extension("bin");
-                                match encode_file::<GameCube>(&input, &output) {
-                                    Ok(_) => println!("Successfully encoded file."),
+                            Some("bin") => {
+                                let output_format = format.unwrap_or(Format::Json);
+                                let output = input.with_extension(output_format.extension());
+                                match decode_file::<GameCube>(&input, &output, output_format, single_line, annotate, &names()) {
+                                    Ok(_) => println!("Successfully decoded file."),
                                     Err(e) => barf(&e.to_string()),
                                 }
                             }
-                            Some("bin") => {
-                                let output = input.with_extension("json");
-                                match decode_file::<GameCube>(&input, &output, single_line) {
-                                    Ok(_) => println!("Successfully decoded file."),
+                            _ => {
+                                let input_format = format.or_else(|| Format::from_path(&input))
+                                    .unwrap_or_else(|| barf("Not a json, csv, toml or set file."));
+                                let output = input.with_extension("bin");
+                                match encode_file::<GameCube>(&input, &output, input_format, compress, &names()) {
+                                    Ok(_) => println!("Successfully encoded file."),
                                     Err(e) => barf(&e.to_string()),
                                 }
                             }
-                            _ => barf("Not a json or a set file."),
                         }
                     }
-                    _ => barf("Not a json or a set file."),
+                    _ => barf("Not a json, csv, toml or set file."),
                 }
             }
         }
@@ -148,70 +385,261 @@ fn main() {
 }
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options] (INPUT | (-d | -e) INPUT OUTPUT)", program);
+    let brief = format!("Usage: {} [options] (INPUT | (-d | -e) INPUT OUTPUT | -t EXPR INPUT [OUTPUT] | (-n X,Y,Z [-k N | -r R] | -b X,Y,Z:X,Y,Z) INPUT | -p SVG [--diff OLD] INPUT)\n\nText files are json, csv or toml, picked by extension or -f.\n\nTransform expressions look like `id in 0x00,0x01 and y > 100 : translate 0,20,0`.\nConditions: `id in ID,...`, `within X,Y,Z X,Y,Z`, `FIELD OP NUMBER` (FIELD: id rx ry rz x y z attr1 attr2 attr3).\nTransforms: `translate X,Y,Z`, `rotate DEGREES [about X,Y,Z]`, `scale FACTOR [about X,Y,Z]`, joined with `then`.", program);
     println!("OnVar's Set Editor ({})", env!("CARGO_PKG_VERSION"));
     print!("{}", opts.usage(&brief));
 }
 
+fn parse_search(matches: &getopts::Matches) -> Option<Search> {
+    let parse_position = |s: &str| Position::from_str(s).unwrap_or_else(|e| barf(&format!("Bad position: {}", e)));
+
+    if let Some(corners) = matches.opt_str("b") {
+        let mut split = corners.splitn(2, ':');
+        let corner1 = parse_position(split.next().unwrap_or(""));
+        let corner2 = parse_position(split.next().unwrap_or_else(|| barf("Box must be given as X,Y,Z:X,Y,Z.")));
+        return Some(Search::Box(corner1, corner2));
+    }
+
+    let point = parse_position(&matches.opt_str("n")?);
+    match (matches.opt_str("r"), matches.opt_str("k")) {
+        (Some(_), Some(_)) => barf("Only one of --radius and --count can be specified."),
+        (Some(radius), None) => Some(Search::Radius(point, f32::from_str(&radius).unwrap_or_else(|_| barf("Radius must be a number.")))),
+        (None, count) => {
+            let count = count.map(|c| usize::from_str(&c).unwrap_or_else(|_| barf("Count must be a number."))).unwrap_or(1);
+            Some(Search::Nearest(point, count))
+        }
+    }
+}
+
 fn barf(err: &str) -> ! {
     println!("Error: {}", err);
     process::exit(1);
 }
 
-fn encode_file<P>(input: &Path, output: &Path) -> Result<(), &'static str>
+fn encode_file<P>(input: &Path, output: &Path, format: Format, compress: bool, names: &Names) -> error::Result<()>
     where P: Platform
 {
-    let json_file = File::open(input).map_err(|_| "Could not open json file.")?;
-    let set_objs = serde_json::from_reader::<_, SetFile>(json_file).map_err(|_| "Could not parse json file.")?;
+    let set_objs = read_text_file(input, format, names.for_file(output).or_else(|| names.for_file(input)))?;
+    warn_object_limit(output, &set_objs);
+    if compress {
+        write_compressed_set_file::<P>(output, &set_objs)
+    }
+    else {
+        write_set_file::<P>(output, &set_objs)
+    }
+}
+
+fn decode_file<P>(input: &Path, output: &Path, format: Format, single_line: bool, annotate: bool, names: &Names) -> error::Result<()>
+    where P: Platform
+{
+    let set_objs = read_set_file::<P>(input)?;
+    warn_object_limit(input, &set_objs);
+
+    write_text_file(output, &set_objs, format, single_line, annotate, names.for_file(input))
+}
 
-    let mut set_file = File::create(output).map_err(|_| "Could not create set file.")?;
-    set_objs.write_data::<P, _>(&mut set_file).map_err(|_| "Could not write set data.")?;
+fn transform_file<P>(input: &Path, output: &Path, query: &Query, format: Option<Format>, single_line: bool, annotate: bool, names: &Names) -> error::Result<usize>
+    where P: Platform
+{
+    let mut set_objs = read_any_file::<P>(input, format, names)?;
+
+    let count = query.apply(&mut set_objs);
+
+    if is_set_file(output) {
+        warn_object_limit(output, &set_objs);
+        write_set_file::<P>(output, &set_objs)?;
+    }
+    else {
+        let output_format = format.or_else(|| Format::from_path(output)).unwrap_or(Format::Json);
+        write_text_file(output, &set_objs, output_format, single_line, annotate, names.for_file(input))?;
+    }
+
+    Ok(count)
+}
+
+fn search_file<P>(input: &Path, search: &Search, format: Option<Format>, names: &Names) -> error::Result<()>
+    where P: Platform
+{
+    let set_objs = read_any_file::<P>(input, format, names)?;
+    let index = SpatialIndex::new(&set_objs);
+
+    let found = match *search {
+        Search::Nearest(ref point, count) => index.nearest(point, count).into_iter().map(|(idx, dist)| (idx, Some(dist))).collect(),
+        Search::Radius(ref point, radius) => index.within_radius(point, radius).into_iter().map(|(idx, dist)| (idx, Some(dist))).collect(),
+        Search::Box(ref corner1, ref corner2) => index.within_box(corner1, corner2).into_iter().map(|idx| (idx, None)).collect::<Vec<_>>(),
+    };
+
+    let names = names.for_file(input);
+    for (idx, dist) in found.iter() {
+        let obj = &set_objs.0[*idx];
+        print!("obj {}: {:04X}", idx, obj.object.0);
+        if let Some(name) = names.and_then(|(table, level)| table.lookup(level, obj.object.0)) {
+            print!(" {}", name);
+        }
+        print!(" at ({}, {}, {})", obj.position.x, obj.position.y, obj.position.z);
+        match dist {
+            Some(dist) => println!(", distance {}", dist),
+            None => println!(),
+        }
+    }
+    println!("Found {} objects.", found.len());
 
     Ok(())
 }
 
-fn decode_file<P>(input: &Path, output: &Path, single_line: bool) -> Result<(), &'static str>
+fn plot_file<P>(input: &Path, old: Option<&Path>, output: &Path, format: Option<Format>, names: &Names, options: &SvgOptions) -> error::Result<()>
     where P: Platform
 {
-    let mut set_file = File::open(input).map_err(|_| "Could not open set file.")?;
-    let set_objs = SetFile::from_read::<P, _>(&mut set_file).map_err(|_| "Could not parse set file.")?;
+    let set_objs = read_any_file::<P>(input, format, names)?;
+    let file_names = names.for_file(input);
+
+    let image = match old {
+        Some(old) => {
+            let old_objs = read_any_file::<P>(old, format, names)?;
+            svg::render_diff(&old_objs, &set_objs, file_names, options)
+        }
+        None => svg::render(&set_objs, file_names, options),
+    };
 
-    let mut json_file = File::create(output).map_err(|_| "Could not create json file.")?;
+    let mut svg_file = File::create(output).in_file(output)?;
+    svg_file.write_all(image.as_bytes()).in_file(output)
+}
 
+fn read_any_file<P>(input: &Path, format: Option<Format>, names: &Names) -> error::Result<SetFile>
+    where P: Platform
+{
+    if is_set_file(input) {
+        read_set_file::<P>(input)
+    }
+    else {
+        let input_format = format.or_else(|| Format::from_path(input)).unwrap_or(Format::Json);
+        read_text_file(input, input_format, names.for_file(input))
+    }
+}
+
+fn is_set_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("bin")
+}
+
+fn read_set_file<P>(input: &Path) -> error::Result<SetFile>
+    where P: Platform
+{
+    let mut set_file = File::open(input).in_file(input)?;
+    SetFile::from_read::<P, _>(&mut set_file).map_err(|e| Error::set_file(e).in_file(input))
+}
+
+fn write_set_file<P>(output: &Path, set_objs: &SetFile) -> error::Result<()>
+    where P: Platform
+{
+    let mut set_file = File::create(output).in_file(output)?;
+    set_objs.write_data::<P, _>(&mut set_file).in_file(output)
+}
+
+fn write_compressed_set_file<P>(output: &Path, set_objs: &SetFile) -> error::Result<()>
+    where P: Platform
+{
+    let mut data = Vec::new();
+    set_objs.write_data::<P, _>(&mut data).in_file(output)?;
+    let compressed = Encoder::new(&data).encode();
+    fs::write(output, compressed).in_file(output)
+}
+
+fn read_text_file(input: &Path, format: Format, names: Option<(&ObjectTable, u16)>) -> error::Result<SetFile> {
+    let text_file = File::open(input).in_file(input)?;
+    match format {
+        Format::Json => format::read_json(text_file, names).in_file(input),
+        Format::Csv => format::read_csv(text_file, names).in_file(input),
+        Format::Toml => format::read_toml(text_file, names).in_file(input),
+    }
+}
+
+fn write_text_file(output: &Path, set_objs: &SetFile, format: Format, single_line: bool, annotate: bool, names: Option<(&ObjectTable, u16)>) -> error::Result<()> {
+    let text_file = File::create(output).in_file(output)?;
+
+    match format {
+        Format::Csv => format::write_csv(text_file, set_objs, names).in_file(output),
+        Format::Toml => format::write_toml(text_file, set_objs, names).in_file(output),
+        Format::Json if annotate => write_json(text_file, &format::annotate(set_objs, names), single_line).in_file(output),
+        Format::Json => write_json(text_file, &set_objs.0, single_line).in_file(output),
+    }
+}
+
+fn write_json<W, T>(mut json_file: W, objs: &[T], single_line: bool) -> error::Result<()>
+    where W: Write,
+          T: Serialize
+{
     if single_line {
         let mut first = true;
-        json_file.write_all(b"[").map_err(|_| "Could not write json data.")?;
-        json_file.write_all(NEWLINE).map_err(|_| "Could not write json data.")?;
-        for obj in set_objs.0 {
+        json_file.write_all(b"[")?;
+        json_file.write_all(NEWLINE)?;
+        for obj in objs.iter() {
             if !first {
-                json_file.write_all(b",").map_err(|_| "Could not write json data.")?;
-                json_file.write_all(NEWLINE).map_err(|_| "Could not write json data.")?;
+                json_file.write_all(b",")?;
+                json_file.write_all(NEWLINE)?;
             }
             else {
                 first = false;
             }
 
-            json_file.write_all(b"  ").map_err(|_| "Could not write json data.")?;
-            serde_json::to_writer(&mut json_file, &obj).map_err(|_| "Could not write json data.")?;
+            json_file.write_all(b"  ")?;
+            serde_json::to_writer(&mut json_file, obj)?;
         }
-        json_file.write_all(NEWLINE).map_err(|_| "Could not write json data.")?;
-        json_file.write_all(b"]").map_err(|_| "Could not write json data.")?;
+        json_file.write_all(NEWLINE)?;
+        json_file.write_all(b"]")?;
     }
     else {
         let mut serializer = Serializer::with_formatter(json_file, Sa2PrettyPrinter::new());
-        set_objs.serialize(&mut serializer).map_err(|_| "Could not write json data.")?;
+        objs.serialize(&mut serializer)?;
     }
 
     Ok(())
 }
 
+/// The object table and the level to look names up in, if one was given.
+struct Names {
+    table: ObjectTable,
+    level: Option<u16>,
+}
+
+impl Names {
+    /// Loads the object table, warning and using the built-in table instead
+    /// if it can't be loaded. Names it doesn't know fail when they're read.
+    fn load(table_path: Option<&Path>, level: Option<u16>) -> Names {
+        let table = match ObjectTable::load(table_path) {
+            Ok((table, _)) => table,
+            Err(e) => {
+                eprintln!("Warning: could not load object table: {}. Using the built-in table.", e);
+                ObjectTable::builtin()
+            }
+        };
+        Names { table, level }
+    }
+
+    /// Falls back to the level in the set file name when none was given.
+    fn for_file(&self, path: &Path) -> Option<(&ObjectTable, u16)> {
+        self.level
+            .or_else(|| SetFileName::from_path(path).map(|name| name.level))
+            .map(|level| (&self.table, level))
+    }
+}
+
+fn warn_object_limit(path: &Path, set_objs: &SetFile) {
+    let limit = SetFileName::from_path(path)
+        .map(|name| name.object_limit())
+        .unwrap_or(OBJECT_LIMIT);
+
+    if set_objs.0.len() > limit {
+        eprintln!("Warning: {} objects exceeds the limit of {}; the game will drop the extra objects.", set_objs.0.len(), limit);
+    }
+}
+
 #[cfg(feature="gui")]
-fn run_gui() {
-    let mut gui = gui::SetEditorGui::new(None);
+fn run_gui(table_path: Option<&Path>) {
+    let mut gui = gui::SetEditorGui::new(None, table_path.map(Path::to_path_buf));
     gui.run().unwrap_or_else(|_| barf("Could not run gui."));
 }
 
 #[cfg(not(feature="gui"))]
-fn run_gui() {
+fn run_gui(_table_path: Option<&Path>) {
     barf("Gui support not compiled in.");
 }
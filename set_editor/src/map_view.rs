@@ -0,0 +1,148 @@
+use sa2_set::Position;
+
+use set_model::{ObjectKey, SetModel};
+
+/// How close to an object, in pixels, a click has to be to pick it.
+const PICK_RADIUS: f64 = 6.0;
+const MIN_SCALE: f64 = 0.001;
+const MAX_SCALE: f64 = 100.0;
+const FIT_MARGIN: f64 = 20.0;
+
+/// The part of the level shown in the map view, looking down with X to the
+/// right and Z down, as in the game's top-down coordinates.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Viewport {
+    pub center_x: f64,
+    pub center_z: f64,
+    /// Pixels per world unit.
+    pub scale: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Viewport {
+    /// A viewport of the given size that shows every position.
+    pub fn fit<'a, I>(positions: I, width: f64, height: f64) -> Viewport
+        where I: Iterator<Item = &'a Position>
+    {
+        let mut bounds: Option<(f64, f64, f64, f64)> = None;
+        for position in positions {
+            let (x, z) = (position.x as f64, position.z as f64);
+            bounds = Some(match bounds {
+                Some((min_x, max_x, min_z, max_z)) => (min_x.min(x), max_x.max(x), min_z.min(z), max_z.max(z)),
+                None => (x, x, z, z),
+            });
+        }
+
+        let (min_x, max_x, min_z, max_z) = bounds.unwrap_or((-500.0, 500.0, -500.0, 500.0));
+        let span_x = (max_x - min_x).max(1.0);
+        let span_z = (max_z - min_z).max(1.0);
+        let scale = ((width - 2.0 * FIT_MARGIN).max(1.0) / span_x).min((height - 2.0 * FIT_MARGIN).max(1.0) / span_z);
+        Viewport {
+            center_x: (min_x + max_x) / 2.0,
+            center_z: (min_z + max_z) / 2.0,
+            scale: scale.clamp(MIN_SCALE, MAX_SCALE),
+            width,
+            height,
+        }
+    }
+
+    pub fn screen_position(&self, x: f64, z: f64) -> (f64, f64) {
+        ((x - self.center_x) * self.scale + self.width / 2.0, (z - self.center_z) * self.scale + self.height / 2.0)
+    }
+
+    pub fn world_position(&self, screen_x: f64, screen_y: f64) -> (f64, f64) {
+        ((screen_x - self.width / 2.0) / self.scale + self.center_x, (screen_y - self.height / 2.0) / self.scale + self.center_z)
+    }
+
+    /// Moves the view along with a drag of `dx`, `dy` pixels.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.center_x -= dx / self.scale;
+        self.center_z -= dy / self.scale;
+    }
+
+    /// Zooms by `factor`, keeping the spot under the cursor where it is.
+    pub fn zoom_at(&mut self, screen_x: f64, screen_y: f64, factor: f64) {
+        let (x, z) = self.world_position(screen_x, screen_y);
+        self.scale = (self.scale * factor).clamp(MIN_SCALE, MAX_SCALE);
+        let (new_x, new_z) = self.world_position(screen_x, screen_y);
+        self.center_x += x - new_x;
+        self.center_z += z - new_z;
+    }
+
+    /// The object drawn nearest to a point on screen, if one is within
+    /// `PICK_RADIUS`.
+    pub fn object_at(&self, model: &SetModel, screen_x: f64, screen_y: f64) -> Option<ObjectKey> {
+        model.iter()
+            .map(|(key, obj)| {
+                let (x, y) = self.screen_position(obj.position.x as f64, obj.position.z as f64);
+                (key, (x - screen_x).powi(2) + (y - screen_y).powi(2))
+            })
+            .filter(|&(_, distance)| distance <= PICK_RADIUS * PICK_RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(key, _)| key)
+    }
+}
+
+/// Converts a hue in degrees with saturation and lightness from 0 to 1 into
+/// RGB components from 0 to 1, for drawing with the same colors as the SVG
+/// plots.
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = (hue % 360.0) / 60.0;
+    let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let m = lightness - chroma / 2.0;
+    (r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetFile, SetObject, Position};
+
+    use set_model::SetModel;
+    use super::{hsl_to_rgb, Viewport};
+
+    fn obj(x: f32, z: f32) -> SetObject {
+        SetObject {
+            position: Position { x, y: 0.0, z },
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_cursor_spot() {
+        let model = SetModel::new(SetFile(vec![obj(-100.0, 0.0), obj(100.0, 50.0)]));
+        let mut viewport = Viewport::fit(model.iter().map(|(_, obj)| &obj.position), 240.0, 200.0);
+        assert_eq!(viewport.screen_position(-100.0, 0.0), (20.0, 75.0));
+
+        let before = viewport.world_position(30.0, 40.0);
+        viewport.zoom_at(30.0, 40.0, 2.0);
+        let after = viewport.world_position(30.0, 40.0);
+        assert!((before.0 - after.0).abs() < 1e-9 && (before.1 - after.1).abs() < 1e-9);
+
+        viewport.pan(10.0, 0.0);
+        assert!((viewport.world_position(40.0, 40.0).0 - after.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn picks_nearest_object() {
+        let model = SetModel::new(SetFile(vec![obj(0.0, 0.0), obj(2.0, 0.0), obj(50.0, 0.0)]));
+        let viewport = Viewport { center_x: 0.0, center_z: 0.0, scale: 1.0, width: 100.0, height: 100.0 };
+        assert_eq!(viewport.object_at(&model, 51.5, 50.0), model.key_at(1));
+        assert_eq!(viewport.object_at(&model, 75.0, 50.0), None);
+    }
+
+    #[test]
+    fn converts_hsl() {
+        assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), (1.0, 0.0, 0.0));
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), (0.0, 0.0, 1.0));
+    }
+}
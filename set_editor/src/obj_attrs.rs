@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::fs::File;
+
+use serde_json;
+use error::{self, ResultExt};
+use obj_table::{self, ObjectInfo, TableSource};
+use sa2_set::SetObject;
+
+/// The attribute data shipped with set_editor, used when no other file is
+/// found.
+const BUILTIN_ATTRS: &str = include_str!("../obj_attrs.json");
+const ATTRS_FILE_NAME: &str = "obj_attrs.json";
+
+/// What one of an object's three attributes means and how to edit it.
+///
+/// An attribute with `values` is picked from a list, one with `flags` is a
+/// set of bits to tick, and anything else is a number. Flags and list values
+/// are whole numbers stored in the attribute's float, so only bits below 24
+/// survive the round trip.
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct AttrInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>,
+    /// Value to name, for attributes that pick one of several options.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<i32, String>,
+    /// Bit number to name, for attributes made of flags.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flags: BTreeMap<u32, String>,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum AttrKind<'a> {
+    Number,
+    Enum(&'a BTreeMap<i32, String>),
+    Flags(&'a BTreeMap<u32, String>),
+}
+
+impl AttrInfo {
+    /// A plain number for attributes the data file doesn't describe, using
+    /// the object table's description if it has one.
+    pub fn plain(index: usize, description: Option<&String>) -> AttrInfo {
+        AttrInfo {
+            name: format!("Attribute {}", index + 1),
+            description: description.cloned(),
+            ..AttrInfo::default()
+        }
+    }
+
+    pub fn kind(&self) -> AttrKind<'_> {
+        if !self.flags.is_empty() {
+            AttrKind::Flags(&self.flags)
+        }
+        else if !self.values.is_empty() {
+            AttrKind::Enum(&self.values)
+        }
+        else {
+            AttrKind::Number
+        }
+    }
+}
+
+/// Attribute descriptions per object, keyed by the object's name in the
+/// object table so one entry covers every level the object appears in.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct AttrTable {
+    objects: BTreeMap<String, Vec<AttrInfo>>,
+}
+
+impl AttrTable {
+    pub fn builtin() -> AttrTable {
+        AttrTable::from_json(BUILTIN_ATTRS).expect("built-in attribute data is invalid")
+    }
+
+    /// Loads the data file at `path` if one was given, otherwise the first
+    /// one found where object tables are looked for, falling back to the
+    /// built-in data.
+    pub fn load(path: Option<&Path>) -> error::Result<(AttrTable, TableSource)> {
+        if let Some(path) = path {
+            return AttrTable::from_file(path).map(|table| (table, TableSource::File(path.to_path_buf())));
+        }
+
+        for path in obj_table::data_file_paths(ATTRS_FILE_NAME) {
+            if path.is_file() {
+                return AttrTable::from_file(&path).map(|table| (table, TableSource::File(path.clone())));
+            }
+        }
+
+        Ok((AttrTable::builtin(), TableSource::Builtin))
+    }
+
+    pub fn from_file(path: &Path) -> error::Result<AttrTable> {
+        let mut text = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut text)).in_file(path)?;
+        AttrTable::from_json(&text).in_file(path)
+    }
+
+    pub fn from_json(text: &str) -> error::Result<AttrTable> {
+        let objects: BTreeMap<String, Vec<AttrInfo>> = serde_json::from_str(text)?;
+        if let Some((name, attrs)) = objects.iter().find(|&(_, attrs)| attrs.len() > 3) {
+            return Err(error::Error::other(format!("{} lists {} attributes, but objects only have three", name, attrs.len())));
+        }
+        Ok(AttrTable {
+            objects: objects.into_iter().map(|(name, attrs)| (name.to_uppercase(), attrs)).collect(),
+        })
+    }
+
+    /// The attributes described for an object, found by its name or any of
+    /// its aliases, ignoring case.
+    pub fn attributes(&self, info: &ObjectInfo) -> Option<&[AttrInfo]> {
+        Some(&info.name).into_iter().chain(info.aliases.iter())
+            .filter_map(|name| self.objects.get(&name.to_uppercase()))
+            .next()
+            .map(|attrs| attrs.as_slice())
+    }
+}
+
+/// `attr1`, `attr2` or `attr3` by index.
+pub fn attribute(obj: &SetObject, index: usize) -> f32 {
+    match index {
+        0 => obj.attr1,
+        1 => obj.attr2,
+        _ => obj.attr3,
+    }
+}
+
+pub fn set_attribute(obj: &mut SetObject, index: usize, value: f32) {
+    match index {
+        0 => obj.attr1 = value,
+        1 => obj.attr2 = value,
+        _ => obj.attr3 = value,
+    }
+}
+
+pub fn has_flag(value: f32, bit: u32) -> bool {
+    (value as u32) & (1 << bit) != 0
+}
+
+/// `value` with flag `bit` set or cleared.
+pub fn with_flag(value: f32, bit: u32, on: bool) -> f32 {
+    let bits = value as u32;
+    (if on { bits | (1 << bit) } else { bits & !(1 << bit) }) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use obj_table::{ObjectInfo, ObjectTable};
+    use super::{AttrTable, AttrKind, with_flag, has_flag};
+
+    #[test]
+    fn looks_up_by_name_or_alias() {
+        let table = AttrTable::from_json(r#"{
+            "kasoku": [
+                {"name": "Speed", "unit": "units/frame", "min": 0},
+                {"name": "Style", "values": {"0": "Normal", "1": "Hidden"}},
+                {"name": "Options", "flags": {"0": "Silent", "2": "Reversed"}}
+            ]
+        }"#).unwrap();
+
+        let info = ObjectInfo { name: String::from("DASH"), aliases: vec![String::from("KASOKU")], ..ObjectInfo::default() };
+        let attrs = table.attributes(&info).unwrap();
+        assert_eq!(attrs[0].kind(), AttrKind::Number);
+        assert_eq!(attrs[0].unit.as_deref(), Some("units/frame"));
+        assert_eq!(attrs[1].kind(), AttrKind::Enum(&attrs[1].values));
+        assert_eq!(attrs[2].kind(), AttrKind::Flags(&attrs[2].flags));
+
+        let other = ObjectInfo { name: String::from("RING"), ..ObjectInfo::default() };
+        assert_eq!(table.attributes(&other), None);
+    }
+
+    #[test]
+    fn rejects_a_fourth_attribute() {
+        let err = AttrTable::from_json(r#"{"RING": [{"name": "A"}, {"name": "B"}, {"name": "C"}, {"name": "D"}]}"#).unwrap_err();
+        assert_eq!(err.to_string(), "RING lists 4 attributes, but objects only have three");
+    }
+
+    #[test]
+    fn bundled_data_covers_the_object_table() {
+        let attrs = AttrTable::builtin();
+        let table = ObjectTable::builtin();
+        for level in [13, 16].iter() {
+            for info in table.objects(*level).values().filter(|info| info.name != "unknown") {
+                assert!(attrs.attributes(info).is_some(), "no attribute data for {}", info.name);
+            }
+        }
+        assert_eq!(attrs.attributes(table.info(13, 7).unwrap()).unwrap()[0].name, "Speed");
+    }
+
+    #[test]
+    fn flags_round_trip() {
+        let value = with_flag(with_flag(0.0, 2, true), 0, true);
+        assert_eq!(value, 5.0);
+        assert!(has_flag(value, 2) && !has_flag(value, 1));
+        assert_eq!(with_flag(value, 2, false), 1.0);
+    }
+}
@@ -1,20 +1,432 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::fs::File;
 
+use dirs;
 use serde_json;
+use error::{self, ResultExt};
+use sa2_set::Stage;
 
-#[derive(Clone,Debug,Deserialize)]
-pub struct ObjectTable(HashMap<u16, HashMap<u16, String>>);
+/// The table shipped with set_editor, used when no other table is found.
+const BUILTIN_TABLE: &str = include_str!("../obj_table.json");
+const TABLE_FILE_NAME: &str = "obj_table.json";
+
+/// Where a loaded object table came from.
+#[derive(Clone,Debug,PartialEq)]
+pub enum TableSource {
+    File(PathBuf),
+    Builtin,
+}
+
+impl fmt::Display for TableSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TableSource::File(ref path) => write!(f, "{}", path.display()),
+            TableSource::Builtin => write!(f, "built-in table"),
+        }
+    }
+}
+
+/// What the table knows about one object ID.
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct ObjectInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Descriptions of `attr1`, `attr2` and `attr3`, in that order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<String>,
+}
+
+impl ObjectInfo {
+    fn matches_name(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Entries can be written as just the name when there is nothing else to
+/// say about an object.
+#[derive(Clone,Debug,Serialize,Deserialize)]
+#[serde(untagged)]
+enum ObjectEntry {
+    Name(String),
+    Info(ObjectInfo),
+}
+
+impl From<ObjectEntry> for ObjectInfo {
+    fn from(entry: ObjectEntry) -> ObjectInfo {
+        match entry {
+            ObjectEntry::Name(name) => ObjectInfo { name, ..ObjectInfo::default() },
+            ObjectEntry::Info(info) => info,
+        }
+    }
+}
+
+impl From<ObjectInfo> for ObjectEntry {
+    fn from(info: ObjectInfo) -> ObjectEntry {
+        if info.aliases.is_empty() && info.category.is_none() && info.attributes.is_empty() {
+            ObjectEntry::Name(info.name)
+        }
+        else {
+            ObjectEntry::Info(info)
+        }
+    }
+}
+
+#[derive(Clone,Debug,Default,Serialize,Deserialize)]
+struct LevelEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Whether the common objects apply to this level.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    common: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    objects: BTreeMap<u16, ObjectEntry>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+struct TableFile {
+    #[serde(default)]
+    common: BTreeMap<u16, ObjectEntry>,
+    levels: BTreeMap<u16, LevelEntry>,
+}
+
+/// The original format: a flat map of level ID to object ID to name.
+type LegacyTableFile = HashMap<u16, HashMap<u16, String>>;
+
+#[derive(Clone,Debug,Default,PartialEq)]
+struct Level {
+    name: Option<String>,
+    common: bool,
+    objects: BTreeMap<u16, ObjectInfo>,
+}
+
+/// Object names and metadata per level.
+///
+/// Objects shared by every stage live in the common list; each level can
+/// override or add to it. Levels the table doesn't mention fall back to the
+/// common list.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct ObjectTable {
+    common: BTreeMap<u16, ObjectInfo>,
+    levels: BTreeMap<u16, Level>,
+}
 
 impl ObjectTable {
-    pub fn from_file(path: &Path) -> Result<ObjectTable, String> {
-        let json_file = File::open(path).map_err(|e| e.to_string())?;
-        serde_json::from_reader::<_, ObjectTable>(json_file).map_err(|e| e.to_string())
+    pub fn builtin() -> ObjectTable {
+        ObjectTable::from_json(BUILTIN_TABLE).expect("built-in object table is invalid")
+    }
+
+    /// Loads the table at `path` if one was given. Otherwise the first table
+    /// found in the user config directory or next to the executable is
+    /// used, falling back to the built-in table.
+    pub fn load(path: Option<&Path>) -> error::Result<(ObjectTable, TableSource)> {
+        if let Some(path) = path {
+            return ObjectTable::from_file(path).map(|table| (table, TableSource::File(path.to_path_buf())));
+        }
+
+        for path in ObjectTable::search_paths() {
+            if path.is_file() {
+                return ObjectTable::from_file(&path).map(|table| (table, TableSource::File(path.clone())));
+            }
+        }
+
+        Ok((ObjectTable::builtin(), TableSource::Builtin))
+    }
+
+    /// Where `load` looks for a table when none is given, in order.
+    pub fn search_paths() -> Vec<PathBuf> {
+        data_file_paths(TABLE_FILE_NAME)
+    }
+
+    pub fn from_file(path: &Path) -> error::Result<ObjectTable> {
+        let mut text = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut text)).in_file(path)?;
+        ObjectTable::from_json(&text).in_file(path)
+    }
+
+    pub fn from_json(text: &str) -> error::Result<ObjectTable> {
+        let value = serde_json::from_str(text)?;
+        ObjectTable::from_value(value)
+    }
+
+    // Tables with a "levels" key use the structured format, anything else is
+    // read as the legacy one.
+    fn from_value(value: serde_json::Value) -> error::Result<ObjectTable> {
+        if value.get("levels").is_some() {
+            let TableFile { common, levels } = serde_json::from_value(value)?;
+            Ok(ObjectTable {
+                common: common.into_iter().map(|(id, entry)| (id, entry.into())).collect(),
+                levels: levels.into_iter()
+                    .map(|(id, level)| {
+                        (id, Level {
+                            name: level.name,
+                            common: level.common,
+                            objects: level.objects.into_iter().map(|(id, entry)| (id, entry.into())).collect(),
+                        })
+                    })
+                    .collect(),
+            })
+        }
+        else {
+            let levels: LegacyTableFile = serde_json::from_value(value)?;
+            Ok(ObjectTable {
+                common: BTreeMap::new(),
+                levels: levels.into_iter()
+                    .map(|(id, objects)| {
+                        (id, Level {
+                            name: None,
+                            common: true,
+                            objects: objects.into_iter().map(|(id, name)| (id, ObjectInfo { name, ..ObjectInfo::default() })).collect(),
+                        })
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    pub fn to_writer<W>(&self, writer: W) -> error::Result<()>
+        where W: Write
+    {
+        let table_file = TableFile {
+            common: self.common.iter().map(|(id, info)| (*id, info.clone().into())).collect(),
+            levels: self.levels.iter()
+                .map(|(id, level)| {
+                    (*id, LevelEntry {
+                        name: level.name.clone(),
+                        common: level.common,
+                        objects: level.objects.iter().map(|(id, info)| (*id, info.clone().into())).collect(),
+                    })
+                })
+                .collect(),
+        };
+        Ok(serde_json::to_writer_pretty(writer, &table_file)?)
+    }
+
+    pub fn info(&self, level: u16, object: u16) -> Option<&ObjectInfo> {
+        match self.levels.get(&level) {
+            Some(level_table) => {
+                level_table.objects.get(&object)
+                    .or_else(|| if level_table.common { self.common.get(&object) } else { None })
+            }
+            None => self.common.get(&object),
+        }
     }
 
     pub fn lookup(&self, level: u16, object: u16) -> Option<&String> {
-        self.0.get(&level).and_then(|h| h.get(&object))
+        self.info(level, object).map(|info| &info.name)
+    }
+
+    pub fn category(&self, level: u16, object: u16) -> Option<&str> {
+        self.info(level, object).and_then(|info| info.category.as_ref()).map(|category| category.as_str())
+    }
+
+    /// Finds the object ID for a name or alias, ignoring case. Level
+    /// overrides win over common objects.
+    pub fn find(&self, level: u16, name: &str) -> Option<u16> {
+        self.levels.get(&level)
+            .and_then(|level_table| level_table.objects.iter().find(|&(_, info)| info.matches_name(name)))
+            .map(|(id, _)| *id)
+            .or_else(|| {
+                self.objects(level).into_iter()
+                    .find(|&(_, info)| info.matches_name(name))
+                    .map(|(id, _)| id)
+            })
+    }
+
+    /// Every object ID and its info as seen from `level`, in ID order.
+    pub fn objects(&self, level: u16) -> BTreeMap<u16, &ObjectInfo> {
+        let mut objects = BTreeMap::new();
+        let level_table = self.levels.get(&level);
+        if level_table.map(|l| l.common).unwrap_or(true) {
+            objects.extend(self.common.iter().map(|(id, info)| (*id, info)));
+        }
+        if let Some(level_table) = level_table {
+            objects.extend(level_table.objects.iter().map(|(id, info)| (*id, info)));
+        }
+        objects
+    }
+
+    /// Lists problems that would make names ambiguous or the table
+    /// otherwise suspicious. An empty list means the table is fine.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        check_objects("common", &self.common.iter().map(|(id, info)| (*id, info)).collect(), None, &mut problems);
+        for (level, level_table) in self.levels.iter() {
+            check_objects(&format!("level {}", level), &self.objects(*level), Some(&level_table.objects), &mut problems);
+            if level_table.name.is_none() && Stage::from_id(*level).is_none() {
+                problems.push(format!("level {}: not a known stage", level));
+            }
+        }
+
+        problems
+    }
+
+    /// Merges `other` into this table. Entries in `other` win; aliases are
+    /// combined. Returns a note for every object whose name changed.
+    pub fn merge(&mut self, other: &ObjectTable) -> Vec<String> {
+        let mut notes = Vec::new();
+
+        merge_objects(&mut self.common, &other.common, "common", &mut notes);
+        for (id, other_level) in other.levels.iter() {
+            let level = self.levels.entry(*id).or_insert_with(|| Level { name: None, common: true, objects: BTreeMap::new() });
+            if other_level.name.is_some() {
+                level.name = other_level.name.clone();
+            }
+            level.common = level.common && other_level.common;
+            merge_objects(&mut level.objects, &other_level.objects, &format!("level {}", id), &mut notes);
+        }
+
+        notes
+    }
+}
+
+/// Where a data file named `file_name` is looked for, in order: the user
+/// config directory, then next to the executable.
+pub fn data_file_paths(file_name: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("set_editor").join(file_name));
+    }
+    if let Some(exe_dir) = env::current_exe().ok().as_ref().and_then(|exe| exe.parent()) {
+        paths.push(exe_dir.join(file_name));
+    }
+    paths
+}
+
+/// Reports empty names, too many attribute descriptions and names shared by
+/// two objects. With `own` set, only problems involving those objects are
+/// reported so common objects aren't reported again for every level.
+fn check_objects(scope: &str, objects: &BTreeMap<u16, &ObjectInfo>, own: Option<&BTreeMap<u16, ObjectInfo>>, problems: &mut Vec<String>) {
+    let is_own = |id: &u16| own.map(|own| own.contains_key(id)).unwrap_or(true);
+    let mut seen: HashMap<String, u16> = HashMap::new();
+    for (id, info) in objects.iter() {
+        if is_own(id) && info.name.trim().is_empty() {
+            problems.push(format!("{}: object {} has an empty name", scope, id));
+        }
+        if is_own(id) && info.attributes.len() > 3 {
+            problems.push(format!("{}: object {} describes {} attributes (at most 3)", scope, id, info.attributes.len()));
+        }
+        for name in Some(&info.name).into_iter().chain(info.aliases.iter()) {
+            match seen.get(&name.to_uppercase()) {
+                Some(other) if other != id => {
+                    if is_own(id) || is_own(other) {
+                        problems.push(format!("{}: name \"{}\" is used by both object {} and object {}", scope, name, other, id));
+                    }
+                }
+                _ => {
+                    seen.insert(name.to_uppercase(), *id);
+                }
+            }
+        }
     }
 }
 
+fn merge_objects(objects: &mut BTreeMap<u16, ObjectInfo>, others: &BTreeMap<u16, ObjectInfo>, scope: &str, notes: &mut Vec<String>) {
+    for (id, other) in others.iter() {
+        match objects.get_mut(id) {
+            Some(info) => {
+                if info.name != other.name {
+                    notes.push(format!("{}: object {} renamed from \"{}\" to \"{}\"", scope, id, info.name, other.name));
+                    if !info.aliases.contains(&info.name) && !other.aliases.contains(&info.name) {
+                        info.aliases.push(info.name.clone());
+                    }
+                    info.name = other.name.clone();
+                }
+                for alias in other.aliases.iter() {
+                    if !info.aliases.contains(alias) && *alias != info.name {
+                        info.aliases.push(alias.clone());
+                    }
+                }
+                if other.category.is_some() {
+                    info.category = other.category.clone();
+                }
+                if !other.attributes.is_empty() {
+                    info.attributes = other.attributes.clone();
+                }
+            }
+            None => {
+                objects.insert(*id, other.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ObjectTable;
+
+    const TABLE: &str = r#"{
+        "common": {
+            "0": "RING",
+            "7": {"name": "KASOKU", "aliases": ["DASH PANEL"], "category": "spring"}
+        },
+        "levels": {
+            "13": {"objects": {"0": "RING (copy)", "56": "E KUMI"}},
+            "20": {"common": false, "objects": {"1": "BOSS"}}
+        }
+    }"#;
+
+    #[test]
+    fn lookup_with_fallbacks() {
+        let table = ObjectTable::from_json(TABLE).unwrap();
+        assert_eq!(table.lookup(13, 0).map(|s| s.as_str()), Some("RING (copy)"));
+        assert_eq!(table.lookup(13, 7).map(|s| s.as_str()), Some("KASOKU"));
+        assert_eq!(table.lookup(40, 0).map(|s| s.as_str()), Some("RING"));
+        assert_eq!(table.lookup(20, 0), None);
+        assert_eq!(table.category(13, 7), Some("spring"));
+
+        assert_eq!(table.find(13, "dash panel"), Some(7));
+        assert_eq!(table.find(13, "ring"), None);
+        assert_eq!(table.find(16, "RING"), Some(0));
+    }
+
+    #[test]
+    fn legacy_format() {
+        let table = ObjectTable::from_json(r#"{"13": {"0": "RING"}}"#).unwrap();
+        assert_eq!(table.lookup(13, 0).map(|s| s.as_str()), Some("RING"));
+        assert_eq!(table.lookup(16, 0), None);
+    }
+
+    #[test]
+    fn validate_and_merge() {
+        let mut table = ObjectTable::from_json(TABLE).unwrap();
+        assert!(table.validate().is_empty());
+
+        let other = ObjectTable::from_json(r#"{"levels": {"13": {"objects": {"57": "E KUMI", "56": "E AI"}}}}"#).unwrap();
+        let notes = table.merge(&other);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(table.lookup(13, 56).map(|s| s.as_str()), Some("E AI"));
+        assert_eq!(table.info(13, 56).unwrap().aliases, vec!["E KUMI".to_string()]);
+        assert_eq!(table.validate().len(), 1);
+
+        let mut written = Vec::new();
+        table.to_writer(&mut written).unwrap();
+        assert_eq!(ObjectTable::from_json(&String::from_utf8(written).unwrap()).unwrap(), table);
+    }
+
+    #[test]
+    fn bundled_table_is_valid() {
+        let table = ObjectTable::builtin();
+        assert_eq!(table.validate(), Vec::<String>::new());
+        assert_eq!(table.lookup(13, 0).map(|s| s.as_str()), Some("RING"));
+        assert_eq!(table.lookup(16, 33).map(|s| s.as_str()), Some("WSMMLS"));
+        // Boss fights and kart races don't use the action stages' objects.
+        assert_eq!(table.lookup(63, 0), None);
+        assert_eq!(table.lookup(70, 0), None);
+    }
+}
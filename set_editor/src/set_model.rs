@@ -0,0 +1,208 @@
+use sa2_set::{SetFile, SetObject};
+
+use history::Edit;
+
+/// Identifies an object for as long as it exists, however the objects around
+/// it are added, removed or sorted. The GUI keeps it in a hidden column so a
+/// row always maps back to the object it shows.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct ObjectKey(pub u32);
+
+/// A set file with a key for each of its objects. Changes go through `Edit`s
+/// so they can be undone; the methods that build them take keys, not row
+/// positions, so they act on the intended objects.
+#[derive(Clone,Debug)]
+pub struct SetModel {
+    set_objs: SetFile,
+    keys: Vec<ObjectKey>,
+    next_key: u32,
+}
+
+impl SetModel {
+    pub fn new(set_objs: SetFile) -> SetModel {
+        let count = set_objs.0.len() as u32;
+        SetModel {
+            set_objs,
+            keys: (0..count).map(ObjectKey).collect(),
+            next_key: count,
+        }
+    }
+
+    pub fn set_file(&self) -> &SetFile {
+        &self.set_objs
+    }
+
+    /// Each object's key and the object, in file order.
+    pub fn iter(&self) -> impl Iterator<Item=(ObjectKey, &SetObject)> {
+        self.keys.iter().cloned().zip(self.set_objs.0.iter())
+    }
+
+    pub fn index_of(&self, key: ObjectKey) -> Option<usize> {
+        self.keys.iter().position(|&k| k == key)
+    }
+
+    pub fn key_at(&self, index: usize) -> Option<ObjectKey> {
+        self.keys.get(index).cloned()
+    }
+
+    pub fn get(&self, key: ObjectKey) -> Option<&SetObject> {
+        self.index_of(key).map(|index| &self.set_objs.0[index])
+    }
+
+    /// The objects in `keys`, in file order however the keys are ordered.
+    pub fn objects(&self, keys: &[ObjectKey]) -> Vec<SetObject> {
+        self.iter()
+            .filter(|&(key, _)| keys.contains(&key))
+            .map(|(_, obj)| *obj)
+            .collect()
+    }
+
+    /// Whichever of `keys` comes last in the file.
+    pub fn last_of(&self, keys: &[ObjectKey]) -> Option<ObjectKey> {
+        keys.iter().cloned().max_by_key(|&key| self.index_of(key))
+    }
+
+    /// The edit that replaces the object `key` with `new`, or `None` if the
+    /// object is gone or wouldn't change.
+    pub fn replace(&self, key: ObjectKey, new: SetObject) -> Option<Edit> {
+        let index = self.index_of(key)?;
+        let old = self.set_objs.0[index];
+        if old == new {
+            None
+        }
+        else {
+            Some(Edit::Replace { index, old, new })
+        }
+    }
+
+    /// The edit that inserts `objs` in order after the object `after`, or at
+    /// the start if that's `None` or gone, along with the new objects' keys.
+    pub fn insert_after(&mut self, after: Option<ObjectKey>, objs: Vec<SetObject>) -> (Vec<ObjectKey>, Edit) {
+        let start = after.and_then(|key| self.index_of(key)).map(|index| index + 1).unwrap_or(0);
+        let mut keys = Vec::new();
+        let mut edits = Vec::new();
+        for (offset, obj) in objs.into_iter().enumerate() {
+            let key = ObjectKey(self.next_key);
+            self.next_key += 1;
+            keys.push(key);
+            edits.push(Edit::Insert { index: start + offset, key, obj });
+        }
+        (keys, Edit::Group(edits))
+    }
+
+    /// The edit that removes every object in `keys`. Keys that are gone or
+    /// repeated are skipped.
+    pub fn remove(&self, keys: &[ObjectKey]) -> Edit {
+        let mut indices: Vec<usize> = keys.iter().filter_map(|&key| self.index_of(key)).collect();
+        // Remove from the back so the indices of the rest stay valid.
+        indices.sort_by(|a, b| b.cmp(a));
+        indices.dedup();
+        Edit::Group(indices.into_iter()
+            .map(|index| Edit::Remove { index, key: self.keys[index], obj: self.set_objs.0[index] })
+            .collect())
+    }
+
+    /// The edit that replaces every object that differs from the one at the
+    /// same index in `changed`, such as a copy a transform was applied to.
+    pub fn replace_all(&self, changed: &SetFile) -> Edit {
+        Edit::Group(self.set_objs.0.iter().zip(changed.0.iter()).enumerate()
+            .filter(|&(_, (old, new))| old != new)
+            .map(|(index, (old, new))| Edit::Replace { index, old: *old, new: *new })
+            .collect())
+    }
+
+    pub fn replace_at(&mut self, index: usize, obj: SetObject) {
+        self.set_objs.0[index] = obj;
+    }
+
+    pub fn insert_at(&mut self, index: usize, key: ObjectKey, obj: SetObject) {
+        self.set_objs.0.insert(index, obj);
+        self.keys.insert(index, key);
+    }
+
+    pub fn remove_at(&mut self, index: usize) {
+        self.set_objs.0.remove(index);
+        self.keys.remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetFile, SetObject, Object};
+
+    use history::History;
+    use super::{ObjectKey, SetModel};
+
+    fn obj(id: u16) -> SetObject {
+        SetObject {
+            object: Object(id),
+            ..SetObject::default()
+        }
+    }
+
+    fn ids(model: &SetModel) -> Vec<u16> {
+        model.iter().map(|(_, obj)| obj.object.0).collect()
+    }
+
+    #[test]
+    fn edits_follow_keys() {
+        let mut model = SetModel::new(SetFile(vec![obj(0), obj(1), obj(2), obj(3), obj(4)]));
+        let mut history = History::new();
+        let keys: Vec<ObjectKey> = model.iter().map(|(key, _)| key).collect();
+
+        // Rows picked in a sorted view come in any order, and removing the
+        // first must not shift which objects the others refer to.
+        let edit = model.remove(&[keys[3], keys[1], keys[3], keys[4]]);
+        history.perform(&mut model, edit);
+        assert_eq!(ids(&model), vec![0, 2]);
+        assert_eq!(model.index_of(keys[2]), Some(1));
+        assert_eq!(model.index_of(keys[1]), None);
+
+        let (new_keys, edit) = model.insert_after(Some(keys[0]), vec![obj(9)]);
+        let new_key = new_keys[0];
+        history.perform(&mut model, edit);
+        assert_eq!(ids(&model), vec![0, 9, 2]);
+        assert_eq!(model.key_at(1), Some(new_key));
+
+        let edit = model.replace(keys[2], obj(7)).unwrap();
+        history.perform(&mut model, edit);
+        assert_eq!(model.get(keys[2]), Some(&obj(7)));
+        assert!(model.replace(keys[2], obj(7)).is_none());
+
+        // Undoing puts objects back under the keys they had.
+        while history.undo(&mut model).is_some() {}
+        assert_eq!(ids(&model), vec![0, 1, 2, 3, 4]);
+        assert_eq!(model.iter().map(|(key, _)| key).collect::<Vec<_>>(), keys);
+        assert_eq!(model.get(new_key), None);
+
+        history.redo(&mut model);
+        history.redo(&mut model);
+        assert_eq!(model.index_of(new_key), Some(1));
+        assert_eq!(model.get(keys[4]), None);
+    }
+
+    #[test]
+    fn copies_paste_after_the_selection() {
+        let mut model = SetModel::new(SetFile(vec![obj(0), obj(1), obj(2), obj(3)]));
+        let keys: Vec<ObjectKey> = model.iter().map(|(key, _)| key).collect();
+
+        let selection = [keys[2], keys[0]];
+        let copied = model.objects(&selection);
+        assert_eq!(copied, vec![obj(0), obj(2)]);
+
+        let after = model.last_of(&selection);
+        assert_eq!(after, Some(keys[2]));
+        let (pasted, edit) = model.insert_after(after, copied);
+        edit.apply(&mut model);
+        assert_eq!(ids(&model), vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(pasted.iter().map(|&key| model.index_of(key).unwrap()).collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn replace_all_records_changes() {
+        let model = SetModel::new(SetFile(vec![obj(0), obj(1), obj(2)]));
+        let mut changed = model.set_file().clone();
+        changed.0[2] = obj(5);
+        assert_eq!(model.replace_all(&changed).describe(), "edit of object 2");
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::{Read, Write};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use dirs;
+use serde_json;
+use error::{self, Error, ResultExt};
+use sa2_set::PlatformKind;
+use columns::ColumnLayout;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+/// How many files File > Open Recent lists.
+const RECENT_LIMIT: usize = 10;
+
+/// What the GUI remembers between launches, kept in the user's config
+/// directory next to the object table.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The level the Level spinner was last on.
+    pub level: u16,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub columns: ColumnLayout,
+    /// Most recent first.
+    pub recent_files: Vec<RecentFile>,
+    /// Where the file choosers start.
+    pub last_dir: Option<PathBuf>,
+    /// The platform `.bin` files are taken to be when the file chooser
+    /// doesn't say.
+    pub default_platform: PlatformKind,
+}
+
+/// A file that was opened or saved, with the platform it was read as if it's
+/// a set binary.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<PlatformKind>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            level: 0,
+            window_width: 1340,
+            window_height: 500,
+            columns: ColumnLayout::default(),
+            recent_files: Vec::new(),
+            last_dir: None,
+            default_platform: PlatformKind::Pc,
+        }
+    }
+}
+
+impl Settings {
+    /// Where settings are kept: `set_editor/settings.json` in the config
+    /// directory, e.g. `~/.config` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("set_editor").join(SETTINGS_FILE_NAME))
+    }
+
+    /// Reads the settings file, or gives the defaults if there isn't one yet.
+    pub fn load() -> error::Result<Settings> {
+        match Settings::path() {
+            Some(ref path) if path.is_file() => Settings::from_file(path),
+            _ => Ok(Settings::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> error::Result<Settings> {
+        let mut text = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut text)).in_file(path)?;
+        Settings::from_json(&text).in_file(path)
+    }
+
+    /// Settings from JSON, with anything missing left at its default.
+    pub fn from_json(text: &str) -> error::Result<Settings> {
+        let mut settings: Settings = serde_json::from_str(text)?;
+        settings.columns = settings.columns.normalized();
+        settings.recent_files.truncate(RECENT_LIMIT);
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> error::Result<()> {
+        let path = Settings::path().ok_or_else(|| Error::other("no config directory to save settings in"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).in_file(parent)?;
+        }
+        let text = serde_json::to_string_pretty(self)?;
+        File::create(&path).and_then(|mut f| f.write_all(text.as_bytes())).in_file(&path)
+    }
+
+    /// Puts `path` at the top of the recent files, and starts the file
+    /// choosers in its directory next time.
+    pub fn add_recent(&mut self, path: &Path, platform: Option<PlatformKind>) {
+        self.recent_files.retain(|recent| recent.path != path);
+        self.recent_files.insert(0, RecentFile { path: path.to_path_buf(), platform });
+        self.recent_files.truncate(RECENT_LIMIT);
+        self.last_dir = path.parent().map(Path::to_path_buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use sa2_set::PlatformKind;
+    use columns::ColumnField;
+    use super::{Settings, RECENT_LIMIT};
+
+    #[test]
+    fn fills_in_missing_settings() {
+        let settings = Settings::from_json(r#"{
+            "level": 13,
+            "default_platform": "game_cube",
+            "columns": [{"field": "attribute1", "format": "hex", "visible": false}]
+        }"#).unwrap();
+
+        assert_eq!(settings.level, 13);
+        assert_eq!(settings.default_platform, PlatformKind::GameCube);
+        assert_eq!(settings.window_width, Settings::default().window_width);
+        assert_eq!(settings.columns.0[0].field, ColumnField::Attribute1);
+        assert!(!settings.columns.0[0].visible);
+        assert_eq!(settings.columns.0.len(), Settings::default().columns.0.len());
+
+        let saved = ::serde_json::to_string(&settings).unwrap();
+        assert_eq!(Settings::from_json(&saved).unwrap(), settings);
+    }
+
+    #[test]
+    fn keeps_recent_files_unique_and_short() {
+        let mut settings = Settings::default();
+        for i in 0..RECENT_LIMIT + 2 {
+            settings.add_recent(&PathBuf::from(format!("sets/set{:04}_s.bin", i)), Some(PlatformKind::Pc));
+        }
+        settings.add_recent(Path::new("sets/set0005_s.bin"), Some(PlatformKind::Dreamcast));
+
+        assert_eq!(settings.recent_files.len(), RECENT_LIMIT);
+        assert_eq!(settings.recent_files[0].path, Path::new("sets/set0005_s.bin"));
+        assert_eq!(settings.recent_files[0].platform, Some(PlatformKind::Dreamcast));
+        assert_eq!(settings.recent_files.iter().filter(|recent| recent.path == Path::new("sets/set0005_s.bin")).count(), 1);
+        assert_eq!(settings.last_dir, Some(PathBuf::from("sets")));
+    }
+}
@@ -0,0 +1,260 @@
+use std::f32::consts::PI;
+use std::fmt::Write;
+
+use sa2_set::{SetFile, SetObject, Position};
+use sa2_set::diff::{self, Change};
+
+use obj_table::ObjectTable;
+
+const WIDTH: f32 = 1000.0;
+const MAX_PANEL_HEIGHT: f32 = 1000.0;
+const MARGIN: f32 = 30.0;
+const MARKER_RADIUS: f32 = 4.0;
+const ARROW_LENGTH: f32 = 12.0;
+
+#[derive(Clone,Copy,Debug,Default)]
+pub struct SvgOptions {
+    /// Draw a side view (X across, Y up) under the top-down view.
+    pub side_view: bool,
+    /// Label each object with its name, or its hex ID if it has none.
+    pub labels: bool,
+}
+
+/// Maps one plane of world space onto a panel of the image.
+struct Projection {
+    min_u: f32,
+    min_v: f32,
+    scale: f32,
+    top: f32,
+    side: bool,
+}
+
+impl Projection {
+    fn fit<'a, I>(positions: I, top: f32, side: bool) -> (Projection, f32)
+        where I: Iterator<Item = &'a Position> + Clone
+    {
+        let (mut min_u, mut max_u, mut min_v, mut max_v) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+        for (idx, position) in positions.enumerate() {
+            let (u, v) = Projection::plane(position, side);
+            if idx == 0 {
+                min_u = u;
+                max_u = u;
+                min_v = v;
+                max_v = v;
+            }
+            min_u = min_u.min(u);
+            max_u = max_u.max(u);
+            min_v = min_v.min(v);
+            max_v = max_v.max(v);
+        }
+
+        let span_u = (max_u - min_u).max(1.0);
+        let span_v = (max_v - min_v).max(1.0);
+        let scale = ((WIDTH - 2.0 * MARGIN) / span_u).min((MAX_PANEL_HEIGHT - 2.0 * MARGIN) / span_v);
+        let height = span_v * scale + 2.0 * MARGIN;
+
+        let projection = Projection { min_u, min_v, scale, top, side };
+        (projection, height)
+    }
+
+    fn plane(position: &Position, side: bool) -> (f32, f32) {
+        if side {
+            (position.x, -position.y)
+        }
+        else {
+            (position.x, position.z)
+        }
+    }
+
+    fn project(&self, position: &Position) -> (f32, f32) {
+        let (u, v) = Projection::plane(position, self.side);
+        (MARGIN + (u - self.min_u) * self.scale, self.top + MARGIN + (v - self.min_v) * self.scale)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Objects are colored by their category when the table has one, so rings,
+/// springs and enemies are easy to tell apart, and by object ID otherwise.
+/// Returns the hue in degrees.
+pub fn object_hue(obj: &SetObject, names: Option<(&ObjectTable, u16)>) -> u32 {
+    match names.and_then(|(table, level)| table.category(level, obj.object.0)) {
+        Some(category) => category.bytes().fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32)) % 360,
+        None => (obj.object.0 as u32 * 47) % 360,
+    }
+}
+
+fn object_color(obj: &SetObject, names: Option<(&ObjectTable, u16)>) -> String {
+    format!("hsl({}, 70%, 45%)", object_hue(obj, names))
+}
+
+fn object_label(obj: &SetObject, names: Option<(&ObjectTable, u16)>) -> String {
+    names.and_then(|(table, level)| table.lookup(level, obj.object.0))
+        .cloned()
+        .unwrap_or_else(|| format!("{:04X}", obj.object.0))
+}
+
+fn draw_object(out: &mut String, projection: &Projection, obj: &SetObject, color: &str, label: Option<&str>) {
+    let (x, y) = projection.project(&obj.position);
+    let _ = write!(out, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\"/>", x, y, MARKER_RADIUS, color);
+
+    // The arrow shows the Y rotation, with zero facing +X.
+    if !projection.side {
+        let theta = obj.rotation.y as f32 * 2.0 * PI / 65536.0;
+        let (dx, dy) = (theta.cos() * ARROW_LENGTH, -theta.sin() * ARROW_LENGTH);
+        let _ = write!(out, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" marker-end=\"url(#arrow)\"/>",
+                       x, y, x + dx, y + dy, color);
+    }
+
+    if let Some(label) = label {
+        let _ = write!(out, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"8\" fill=\"{}\">{}</text>", x + MARKER_RADIUS + 2.0, y - MARKER_RADIUS, color, escape(label));
+    }
+    out.push('\n');
+}
+
+struct Panels {
+    projections: Vec<Projection>,
+    height: f32,
+}
+
+fn layout<'a, I>(positions: I, options: &SvgOptions) -> Panels
+    where I: Iterator<Item = &'a Position> + Clone
+{
+    let (top_down, top_height) = Projection::fit(positions.clone(), 0.0, false);
+    let mut projections = vec![top_down];
+    let mut height = top_height;
+
+    if options.side_view {
+        let (side, side_height) = Projection::fit(positions, height, true);
+        projections.push(side);
+        height += side_height;
+    }
+
+    Panels { projections, height }
+}
+
+fn header(out: &mut String, panels: &Panels) {
+    let _ = writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{:.0}\" viewBox=\"0 0 {} {:.0}\">", WIDTH, panels.height, WIDTH, panels.height);
+    out.push_str("<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"4\" markerHeight=\"4\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"context-stroke\"/></marker></defs>\n");
+    out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    for projection in panels.projections.iter() {
+        let title = if projection.side { "Side (X, Y)" } else { "Top (X, Z)" };
+        let _ = writeln!(out, "<text x=\"5\" y=\"{:.1}\" font-size=\"12\" font-family=\"sans-serif\">{}</text>", projection.top + 15.0, title);
+    }
+}
+
+/// Plots every object of `set_objs`, colored by category or object ID.
+pub fn render(set_objs: &SetFile, names: Option<(&ObjectTable, u16)>, options: &SvgOptions) -> String {
+    let panels = layout(set_objs.0.iter().map(|obj| &obj.position), options);
+    let mut out = String::new();
+    header(&mut out, &panels);
+
+    for projection in panels.projections.iter() {
+        for obj in set_objs.0.iter() {
+            let label = if options.labels { Some(object_label(obj, names)) } else { None };
+            draw_object(&mut out, projection, obj, &object_color(obj, names), label.as_deref());
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+const UNCHANGED_COLOR: &str = "#bbbbbb";
+const ADDED_COLOR: &str = "#2ca02c";
+const REMOVED_COLOR: &str = "#d62728";
+const MOVED_COLOR: &str = "#ff7f0e";
+const MODIFIED_COLOR: &str = "#9467bd";
+
+/// Plots the objects of `new`, highlighting what changed since `old`. Moved
+/// objects get a dashed line back to where they were.
+pub fn render_diff(old: &SetFile, new: &SetFile, names: Option<(&ObjectTable, u16)>, options: &SvgOptions) -> String {
+    let changes = diff::diff(old, new);
+    let panels = layout(old.0.iter().chain(new.0.iter()).map(|obj| &obj.position), options);
+    let mut out = String::new();
+    header(&mut out, &panels);
+
+    for projection in panels.projections.iter() {
+        // Unchanged objects go first so the changes are drawn on top of them.
+        let mut ordered = changes.clone();
+        ordered.sort_by_key(|change| match *change {
+            Change::Unchanged(..) => 0,
+            _ => 1,
+        });
+
+        for change in ordered {
+            let (obj, color) = match change {
+                Change::Unchanged(_, idx) => (&new.0[idx], UNCHANGED_COLOR),
+                Change::Added(idx) => (&new.0[idx], ADDED_COLOR),
+                Change::Removed(idx) => (&old.0[idx], REMOVED_COLOR),
+                Change::Modified(_, idx) => (&new.0[idx], MODIFIED_COLOR),
+                Change::Moved(old_idx, idx) => {
+                    let (x1, y1) = projection.project(&old.0[old_idx].position);
+                    let (x2, y2) = projection.project(&new.0[idx].position);
+                    let _ = writeln!(out, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-dasharray=\"3,3\"/>", x1, y1, x2, y2, MOVED_COLOR);
+                    (&new.0[idx], MOVED_COLOR)
+                }
+            };
+
+            let changed = !matches!(change, Change::Unchanged(..));
+            let label = if options.labels && changed { Some(object_label(obj, names)) } else { None };
+            draw_object(&mut out, projection, obj, color, label.as_deref());
+        }
+    }
+
+    let count = |f: &dyn Fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+    let legend = [
+        (ADDED_COLOR, "added", count(&|c| matches!(*c, Change::Added(..)))),
+        (REMOVED_COLOR, "removed", count(&|c| matches!(*c, Change::Removed(..)))),
+        (MOVED_COLOR, "moved", count(&|c| matches!(*c, Change::Moved(..)))),
+        (MODIFIED_COLOR, "modified", count(&|c| matches!(*c, Change::Modified(..)))),
+        (UNCHANGED_COLOR, "unchanged", count(&|c| matches!(*c, Change::Unchanged(..)))),
+    ];
+    for (idx, &(color, text, num)) in legend.iter().enumerate() {
+        let _ = writeln!(out, "<text x=\"{}\" y=\"15\" font-size=\"12\" font-family=\"sans-serif\" fill=\"{}\">{} {}</text>", 120 + idx * 110, color, num, text);
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use sa2_set::{SetFile, SetObject, Object, Position};
+
+    use super::{render, render_diff, SvgOptions};
+
+    fn obj(id: u16, x: f32, z: f32) -> SetObject {
+        SetObject {
+            object: Object(id),
+            position: Position { x, y: 0.0, z },
+            ..SetObject::default()
+        }
+    }
+
+    #[test]
+    fn render_plots_every_object() {
+        let set_objs = SetFile(vec![obj(0, 0.0, 0.0), obj(0x0A, 100.0, 50.0)]);
+        let options = SvgOptions { side_view: true, labels: true };
+        let svg = render(&set_objs, None, &options);
+
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.contains(">000A</text>"));
+    }
+
+    #[test]
+    fn render_diff_highlights_changes() {
+        let old = SetFile(vec![obj(0, 0.0, 0.0), obj(1, 10.0, 0.0)]);
+        let new = SetFile(vec![obj(0, 0.0, 0.0), obj(1, 20.0, 0.0), obj(2, 5.0, 5.0)]);
+        let svg = render_diff(&old, &new, None, &SvgOptions::default());
+
+        assert!(svg.contains("1 added"));
+        assert!(svg.contains("1 moved"));
+        assert!(svg.contains("1 unchanged"));
+        assert!(svg.contains("stroke-dasharray"));
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{self, DebouncedEvent, RecursiveMode, Watcher};
+
+use error::{self, Error};
+use format::Format;
+
+/// How long a file has to be quiet before it's converted, so editors that
+/// save in several steps only trigger one conversion.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Calls `on_change` with every file of `format` under `source` that is
+/// created or saved, until the watcher fails.
+pub fn watch<F>(source: &Path, format: Format, mut on_change: F) -> error::Result<()>
+    where F: FnMut(&Path)
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::watcher(tx, DEBOUNCE).map_err(|e| Error::other(e.to_string()).in_file(source))?;
+    watcher.watch(source, RecursiveMode::Recursive).map_err(|e| Error::other(e.to_string()).in_file(source))?;
+
+    for event in rx {
+        match event {
+            DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path)
+                if Format::from_path(&path) == Some(format) && path.is_file() => {
+                on_change(&path);
+            }
+            DebouncedEvent::Error(e, path) => {
+                let err = Error::other(e.to_string());
+                return Err(match path {
+                    Some(path) => err.in_file(&path),
+                    None => err.in_file(source),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the set file for `changed` goes: the same place relative to
+/// `target` as `changed` is to `source`, as `.prs` when compressed.
+pub fn output_path(source: &Path, changed: &Path, target: &Path, compress: bool) -> PathBuf {
+    let relative = changed.strip_prefix(source)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| changed.file_name().map(PathBuf::from).unwrap_or_default());
+    target.join(relative).with_extension(if compress { "prs" } else { "bin" })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::output_path;
+
+    #[test]
+    fn mirrors_source_layout() {
+        let source = Path::new("sets");
+        let target = Path::new("game/gd_PC");
+        assert_eq!(output_path(source, &source.join("set0013_s.json"), target, false), target.join("set0013_s.bin"));
+        assert_eq!(output_path(source, &source.join("hard").join("set0013_hd_s.json"), target, true), target.join("hard").join("set0013_hd_s.prs"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use sa2_set::{SetFile, SetFileName, Platform, PlatformKind, Pc, GameCube, Dreamcast, OBJECT_LIMIT, has_extension};
+use sa2_text::Sa2TextTable;
+use prs_util::encoder::Encoder;
+use afs_util::AfsWriter;
+use serde_json;
+
+use error::{Error, Result, ResultExt};
+use manifest::{Entry, Project};
+
+/// An output that was written, and anything worth knowing about it.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Built {
+    pub output: PathBuf,
+    pub size: usize,
+    pub warning: Option<String>,
+}
+
+/// Builds every file in `project` into `game_dir`, creating directories as
+/// needed. Stops at the first file that can't be built.
+pub fn build(project: &Project, game_dir: &Path) -> Result<Vec<Built>> {
+    let mut built = Vec::new();
+
+    for entry in project.sets.iter() {
+        let source = project.source(&entry.source);
+        let set_objs = read_set(&source, project.platform)?;
+        let warning = check_object_limit(&entry.output, &set_objs);
+        let data = match project.platform {
+            PlatformKind::Pc => set_data::<Pc>(&set_objs),
+            PlatformKind::GameCube => set_data::<GameCube>(&set_objs),
+            PlatformKind::Dreamcast => set_data::<Dreamcast>(&set_objs),
+        };
+        built.push(write_output(game_dir, entry, data, false, warning)?);
+    }
+
+    for entry in project.texts.iter() {
+        let source = project.source(&entry.source);
+        let data = if is_json(&source) {
+            let table = read_text_table(&source)?;
+            let mut data = Vec::new();
+            table.to_writer(&mut data).in_file(&source)?;
+            data
+        }
+        else {
+            fs::read(&source).in_file(&source)?
+        };
+        built.push(write_output(game_dir, entry, data, !is_prs(&source), None)?);
+    }
+
+    for entry in project.textures.iter() {
+        let source = project.source(&entry.source);
+        let data = fs::read(&source).in_file(&source)?;
+        built.push(write_output(game_dir, entry, data, false, None)?);
+    }
+
+    for archive in project.archives.iter() {
+        let output = game_dir.join(&archive.output);
+        let mut files = Vec::with_capacity(archive.files.len());
+        for file in archive.files.iter() {
+            let source = project.source(file);
+            files.push(BufReader::new(File::open(&source).in_file(&source)?));
+        }
+
+        create_parent(&output)?;
+        let mut data = Cursor::new(Vec::new());
+        AfsWriter::new(&mut data, files).write().in_file(&output)?;
+        let data = data.into_inner();
+        fs::write(&output, &data).in_file(&output)?;
+        built.push(Built { output, size: data.len(), warning: None });
+    }
+
+    Ok(built)
+}
+
+/// Reads a set file from JSON, or from a binary for `platform`.
+fn read_set(source: &Path, platform: PlatformKind) -> Result<SetFile> {
+    if is_json(source) {
+        let text = fs::read_to_string(source).in_file(source)?;
+        return serde_json::from_str(&text).in_file(source);
+    }
+
+    let mut file = BufReader::new(File::open(source).in_file(source)?);
+    let read = match platform {
+        PlatformKind::Pc => SetFile::from_read::<Pc, _>(&mut file),
+        PlatformKind::GameCube => SetFile::from_read::<GameCube, _>(&mut file),
+        PlatformKind::Dreamcast => SetFile::from_read::<Dreamcast, _>(&mut file),
+    };
+    read.map_err(|e| Error::data(e).in_file(source))
+}
+
+fn set_data<P>(set_objs: &SetFile) -> Vec<u8>
+    where P: Platform
+{
+    let mut data = Vec::new();
+    set_objs.write_data::<P, _>(&mut data).expect("writing to memory can't fail");
+    data
+}
+
+fn check_object_limit(output: &Path, set_objs: &SetFile) -> Option<String> {
+    let limit = SetFileName::from_path(output).map(|name| name.object_limit()).unwrap_or(OBJECT_LIMIT);
+    if set_objs.0.len() > limit {
+        Some(format!("{} objects, but the game only loads the first {}", set_objs.0.len(), limit))
+    }
+    else {
+        None
+    }
+}
+
+fn read_text_table(source: &Path) -> Result<Sa2TextTable> {
+    let text = fs::read_to_string(source).in_file(source)?;
+    serde_json::from_str(&text).in_file(source)
+}
+
+/// Writes `data` to `entry`'s output, PRS compressing it if the entry asks
+/// to or, when it doesn't say, if `compress_by_default` is set.
+fn write_output(game_dir: &Path, entry: &Entry, data: Vec<u8>, compress_by_default: bool, warning: Option<String>) -> Result<Built> {
+    let output = game_dir.join(&entry.output);
+    let data = if entry.compress.unwrap_or(compress_by_default) {
+        Encoder::new(&data).encode()
+    }
+    else {
+        data
+    };
+
+    create_parent(&output)?;
+    fs::write(&output, &data).in_file(&output)?;
+    Ok(Built { output, size: data.len(), warning })
+}
+
+fn create_parent(output: &Path) -> Result<()> {
+    match output.parent() {
+        Some(parent) => fs::create_dir_all(parent).in_file(parent),
+        None => Ok(()),
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    has_extension(path, "json")
+}
+
+/// Text sources that are already compressed are copied as they are.
+fn is_prs(path: &Path) -> bool {
+    has_extension(path, "prs")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+
+    use sa2_set::{SetFile, SetObject, Object, Pc};
+    use afs_util::AfsReader;
+    use prs_util::decoder::Decoder;
+    use serde_json;
+
+    use manifest::Project;
+    use super::build;
+
+    #[test]
+    fn builds_into_game_layout() {
+        let root = env::temp_dir().join(format!("stage_project_build_{}", ::std::process::id()));
+        let game_dir = root.join("game");
+        fs::create_dir_all(root.join("voice")).unwrap();
+
+        let set_objs = SetFile(vec![SetObject { object: Object(0x0A), ..SetObject::default() }]);
+        fs::write(root.join("set0013_s.json"), serde_json::to_string(&set_objs).unwrap()).unwrap();
+        fs::write(root.join("stage.gvm"), b"GVMH").unwrap();
+        fs::write(root.join("voice/0.adx"), b"first").unwrap();
+        fs::write(root.join("voice/1.adx"), b"second").unwrap();
+
+        let project = Project::from_toml(r#"
+            name = "City Escape"
+            platform = "pc"
+            [[set]]
+            source = "set0013_s.json"
+            output = "gd_PC/set0013_s.bin"
+            [[texture]]
+            source = "stage.gvm"
+            output = "gd_PC/PRS/stage.prs"
+            compress = true
+            [[afs]]
+            files = ["voice/0.adx", "voice/1.adx"]
+            output = "gd_PC/event_adx.afs"
+        "#, &root).unwrap();
+        let built = build(&project, &game_dir).unwrap();
+
+        let set_data = fs::read(game_dir.join("gd_PC/set0013_s.bin")).unwrap();
+        let texture = fs::read(game_dir.join("gd_PC/PRS/stage.prs")).unwrap();
+        let archive = fs::read(game_dir.join("gd_PC/event_adx.afs")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(built.len(), 3);
+        assert!(built.iter().all(|built| built.warning.is_none()));
+        let read_back = SetFile::from_read::<Pc, _>(&mut Cursor::new(set_data)).unwrap();
+        assert_eq!(read_back.0, set_objs.0);
+        assert_eq!(Decoder::new(texture.as_slice()).decode_to_vec().unwrap(), b"GVMH");
+        assert_eq!(AfsReader::new(Cursor::new(archive)).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,124 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
+
+use serde_json;
+use toml;
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Why a project couldn't be read or built. `path` is the source, output or
+/// manifest the problem was found in, so a failed build can point at the
+/// entry to fix.
+#[derive(Debug)]
+pub struct Error {
+    path: Option<PathBuf>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    /// A set or text binary ended early or couldn't be read.
+    Data(io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// The manifest parsed but asks for something that can't be done, such
+    /// as writing outside the game directory.
+    Manifest(String),
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error { path: None, kind }
+    }
+
+    pub fn data(err: io::Error) -> Error {
+        Error::new(ErrorKind::Data(err))
+    }
+
+    pub fn manifest<S>(message: S) -> Error
+        where S: Into<String>
+    {
+        Error::new(ErrorKind::Manifest(message.into()))
+    }
+
+    /// Names the file the error is about. The first file named sticks, so a
+    /// bad source read while building an entry isn't blamed on the manifest.
+    pub fn in_file(mut self, path: &Path) -> Error {
+        if self.path.is_none() {
+            self.path = Some(path.to_path_buf());
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref path) = self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Io(ref e) => write!(f, "{}", e),
+            ErrorKind::Data(ref e) => write!(f, "not a valid file for this platform ({})", e),
+            ErrorKind::Json(ref e) => write!(f, "{}", e),
+            ErrorKind::Toml(ref e) => write!(f, "{}", e),
+            ErrorKind::Manifest(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref e) | ErrorKind::Data(ref e) => Some(e),
+            ErrorKind::Json(ref e) => Some(e),
+            ErrorKind::Toml(ref e) => Some(e),
+            ErrorKind::Manifest(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new(ErrorKind::Io(err))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::new(ErrorKind::Json(err))
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Error {
+        Error::new(ErrorKind::Toml(err))
+    }
+}
+
+/// `in_file` for any result whose error converts into `Error`, so builder
+/// steps can write `fs::read(&source).in_file(&source)?`.
+pub trait ResultExt<T> {
+    fn in_file(self, path: &Path) -> Result<T>;
+}
+
+impl<T, E> ResultExt<T> for result::Result<T, E>
+    where E: Into<Error>
+{
+    fn in_file(self, path: &Path) -> Result<T> {
+        self.map_err(|e| e.into().in_file(path))
+    }
+}
@@ -0,0 +1,17 @@
+extern crate sa2_set;
+extern crate sa2_text;
+extern crate prs_util;
+extern crate afs_util;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub mod error;
+pub mod manifest;
+pub mod builder;
+
+pub use error::{Error, ErrorKind, Result};
+pub use manifest::{Project, Entry, Archive};
+pub use builder::{build, Built};
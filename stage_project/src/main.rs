@@ -0,0 +1,51 @@
+extern crate stage_project;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use stage_project::Project;
+
+const USAGE: &str = "Usage: stage_project build PROJECT.toml GAME_DIR
+       stage_project check PROJECT.toml";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match (args.first().map(String::as_str), args.len()) {
+        (Some("build"), 3) => {
+            let project = load(&args[1]);
+            let built = stage_project::build(&project, Path::new(&args[2])).unwrap_or_else(|e| barf(&e.to_string()));
+            for built in built.iter() {
+                println!("{} ({} bytes)", built.output.display(), built.size);
+                if let Some(ref warning) = built.warning {
+                    println!("    Warning: {}", warning);
+                }
+            }
+            println!("Built {} files for {}.", built.len(), project.name);
+        }
+        (Some("check"), 2) => {
+            let project = load(&args[1]);
+            let missing: Vec<_> = project.sources().into_iter().filter(|source| !source.is_file()).collect();
+            for source in missing.iter() {
+                println!("Missing: {}", source.display());
+            }
+            if !missing.is_empty() {
+                process::exit(1);
+            }
+            println!("{}: {} files to build, all sources present.", project.name, project.outputs().len());
+        }
+        _ => {
+            println!("{}", USAGE);
+            process::exit(1);
+        }
+    }
+}
+
+fn load(path: &str) -> Project {
+    Project::from_file(Path::new(path)).unwrap_or_else(|e| barf(&e.to_string()))
+}
+
+fn barf(err: &str) -> ! {
+    println!("Error: {}", err);
+    process::exit(1);
+}
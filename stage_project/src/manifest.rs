@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use toml;
+use sa2_set::PlatformKind;
+use error::{Error, Result, ResultExt};
+
+/// A stage's files and where each goes in the game's directory, read from a
+/// TOML manifest. Sources are relative to the manifest and outputs to the
+/// game directory the project is built into.
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    pub name: String,
+    /// The stage's level ID, for reference.
+    #[serde(default)]
+    pub level: Option<u16>,
+    /// Decides the byte order of the stage's set files.
+    pub platform: PlatformKind,
+    /// Set files, from JSON as `set_editor` writes it or from binaries.
+    #[serde(default, rename = "set")]
+    pub sets: Vec<Entry>,
+    /// Text tables such as hints, from JSON as `edit_hints` writes it or
+    /// from binaries.
+    #[serde(default, rename = "text")]
+    pub texts: Vec<Entry>,
+    /// Texture archives, copied as they are.
+    #[serde(default, rename = "texture")]
+    pub textures: Vec<Entry>,
+    /// AFS archives packed from a list of files, such as voice clips.
+    #[serde(default, rename = "afs")]
+    pub archives: Vec<Archive>,
+    /// The directory sources are found in, the manifest's own.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// One file to build.
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    /// Whether to PRS compress the output. Text tables are compressed unless
+    /// told otherwise, as the game expects; everything else isn't.
+    #[serde(default)]
+    pub compress: Option<bool>,
+}
+
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Archive {
+    /// The archive's files in order; their position is their index in it.
+    pub files: Vec<PathBuf>,
+    pub output: PathBuf,
+}
+
+impl Project {
+    pub fn from_file(path: &Path) -> Result<Project> {
+        let text = fs::read_to_string(path).in_file(path)?;
+        let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Project::from_toml(&text, &root).in_file(path)
+    }
+
+    /// Reads a manifest whose sources are relative to `root`.
+    pub fn from_toml(text: &str, root: &Path) -> Result<Project> {
+        let mut project: Project = toml::from_str(text)?;
+        project.root = root.to_path_buf();
+        project.validate()?;
+        Ok(project)
+    }
+
+    /// Where `source` is on disk.
+    pub fn source(&self, source: &Path) -> PathBuf {
+        self.root.join(source)
+    }
+
+    /// Every output, in the order they're built.
+    pub fn outputs(&self) -> Vec<&Path> {
+        self.sets.iter().chain(self.texts.iter()).chain(self.textures.iter())
+            .map(|entry| entry.output.as_path())
+            .chain(self.archives.iter().map(|archive| archive.output.as_path()))
+            .collect()
+    }
+
+    /// Every source, in the order they're read.
+    pub fn sources(&self) -> Vec<PathBuf> {
+        self.sets.iter().chain(self.texts.iter()).chain(self.textures.iter())
+            .map(|entry| self.source(&entry.source))
+            .chain(self.archives.iter().flat_map(|archive| archive.files.iter().map(move |file| self.source(file))))
+            .collect()
+    }
+
+    /// Checks that every output stays inside the game directory and that no
+    /// two entries write the same file.
+    fn validate(&self) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        for output in self.outputs() {
+            let inside = output.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+            if !inside || output.as_os_str().is_empty() {
+                return Err(Error::manifest(format!("output \"{}\" must be a path inside the game directory", output.display())));
+            }
+            if !seen.insert(output) {
+                return Err(Error::manifest(format!("\"{}\" is the output of more than one entry", output.display())));
+            }
+        }
+
+        if let Some(archive) = self.archives.iter().find(|archive| archive.files.is_empty()) {
+            return Err(Error::manifest(format!("archive \"{}\" has no files", archive.output.display())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use sa2_set::PlatformKind;
+    use super::Project;
+
+    #[test]
+    fn reads_manifests() {
+        let project = Project::from_toml(r#"
+            name = "City Escape"
+            level = 13
+            platform = "game_cube"
+
+            [[set]]
+            source = "set/set0013_s.json"
+            output = "gd_PC/set0013_s.bin"
+
+            [[text]]
+            source = "text/hints.json"
+            output = "gd_PC/eh0013e.prs"
+
+            [[afs]]
+            files = ["voice/0.adx", "voice/1.adx"]
+            output = "gd_PC/event_adx.afs"
+        "#, Path::new("mods/city")).unwrap();
+
+        assert_eq!(project.platform, PlatformKind::GameCube);
+        assert_eq!(project.level, Some(13));
+        assert_eq!(project.sets[0].compress, None);
+        assert_eq!(project.outputs().len(), 3);
+        assert_eq!(project.sources()[3], PathBuf::from("mods/city/voice/1.adx"));
+    }
+
+    #[test]
+    fn rejects_unsafe_outputs() {
+        let escapes = Project::from_toml(r#"
+            name = "Bad"
+            platform = "pc"
+            [[texture]]
+            source = "a.gvm"
+            output = "../a.gvm"
+        "#, Path::new("."));
+        assert!(escapes.is_err());
+
+        let twice = Project::from_toml(r#"
+            name = "Bad"
+            platform = "pc"
+            [[texture]]
+            source = "a.gvm"
+            output = "a.gvm"
+            [[texture]]
+            source = "b.gvm"
+            output = "a.gvm"
+        "#, Path::new("."));
+        assert!(twice.unwrap_err().to_string().contains("more than one entry"));
+    }
+}